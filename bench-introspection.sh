@@ -0,0 +1,76 @@
+#!/bin/bash
+
+# Benchmark for reading every table schema (`mysql` tool with `all-tables`) against a seeded
+# local MySQL. Set BIN to the binary of another build to compare it, and SEED=0 to reuse the
+# database seeded by a previous run.
+
+DB_HOST="${DB_HOST:-127.0.0.1}"
+DB_PORT="${DB_PORT:-3306}"
+DB_USER="${DB_USER:-root}"
+DB_PASS="${DB_PASS:-}"
+DB_NAME="${DB_NAME:-mcp_bench}"
+TABLES="${TABLES:-500}"
+RUNS="${RUNS:-5}"
+SEED="${SEED:-1}"
+BIN="${BIN:-target/release/mcp-server-mysql}"
+
+set -e
+
+MYSQL=(mysql --host "$DB_HOST" --port "$DB_PORT" --user "$DB_USER")
+SERVER_ARGS=(--host "$DB_HOST" --port "$DB_PORT" --username "$DB_USER" --database "$DB_NAME"
+    --schema-cache-ttl 0 --max-concurrent-requests 1)
+if [ -n "$DB_PASS" ]; then
+    MYSQL+=(--password="$DB_PASS")
+    SERVER_ARGS+=(--password "$DB_PASS")
+fi
+
+if [ "$SEED" = "1" ]; then
+    echo "Seeding $DB_NAME with $TABLES tables..."
+    {
+        echo "DROP DATABASE IF EXISTS \`$DB_NAME\`;"
+        echo "CREATE DATABASE \`$DB_NAME\`;"
+        echo "USE \`$DB_NAME\`;"
+        echo "CREATE TABLE t0 (id INT PRIMARY KEY, name VARCHAR(100) NOT NULL, UNIQUE KEY uk_name (name));"
+        # Every table references the one before it, so foreign keys and referenced_by are both populated
+        for i in $(seq 1 $((TABLES - 1))); do
+            echo "CREATE TABLE t$i (
+                id INT PRIMARY KEY AUTO_INCREMENT,
+                parent_id INT NOT NULL,
+                status ENUM('active', 'inactive') NOT NULL DEFAULT 'active',
+                amount DECIMAL(10, 2) CHECK (amount >= 0),
+                note TEXT COMMENT 'Free text',
+                created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                KEY idx_status_created (status, created_at),
+                CONSTRAINT fk_t${i}_parent FOREIGN KEY (parent_id) REFERENCES t$((i - 1)) (id)
+            );"
+        done
+    } | "${MYSQL[@]}"
+fi
+
+if [ ! -x "$BIN" ]; then
+    cargo build --release
+fi
+
+requests() {
+    echo '{"jsonrpc":"2.0","id":0,"method":"initialize","params":{}}'
+    for run in $(seq 1 "$RUNS"); do
+        echo "{\"jsonrpc\":\"2.0\",\"id\":$run,\"method\":\"tools/call\",\"params\":{\"name\":\"mysql\",\"arguments\":{\"table_name\":\"all-tables\"}}}"
+    done
+}
+
+questions() {
+    "${MYSQL[@]}" --skip-column-names -e "SHOW GLOBAL STATUS LIKE 'Questions'" | cut -f2
+}
+
+echo "Reading all $TABLES table schemas $RUNS times with $BIN..."
+questions_before=$(questions)
+start=$(date +%s%N)
+requests | "$BIN" "${SERVER_ARGS[@]}" > /dev/null
+end=$(date +%s%N)
+questions_after=$(questions)
+
+elapsed_ms=$(((end - start) / 1000000))
+echo "Total: ${elapsed_ms} ms, including connecting"
+echo "Per run: $((elapsed_ms / RUNS)) ms"
+# Questions counts every client's statements, so run this on an otherwise idle server
+echo "Statements per run: about $(((questions_after - questions_before) / RUNS))"
@@ -0,0 +1,119 @@
+// In-memory cache of introspected table schemas.
+//
+// Reading a table schema takes several information_schema queries, so schemas and table lists
+// are kept for `--schema-cache-ttl` seconds per session. DDL run through the `query` tool drops
+// everything at once, but only in its own session: other HTTP sessions sharing the pool keep
+// their entries until they expire. Table lists are remembered across invalidation so the server can tell
+// whether the set of tables, and therefore the resource list, actually changed.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+// Table names per database and when they were read, `None` once invalidated
+type TableNames = HashMap<String, (Option<Instant>, Vec<String>)>;
+
+pub struct SchemaCache {
+    // Zero disables caching
+    ttl: Duration,
+    schemas: Mutex<HashMap<(String, String), (Instant, Value)>>,
+    table_names: Mutex<TableNames>,
+}
+
+impl SchemaCache {
+    pub fn new(ttl: Duration) -> Self {
+        SchemaCache {
+            ttl,
+            schemas: Mutex::new(HashMap::new()),
+            table_names: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the cached schema of a table if it has not expired.
+    pub fn schema(&self, database: &str, table_name: &str) -> Option<Value> {
+        let schemas = self.schemas.lock().unwrap();
+        let (fetched, schema) = schemas.get(&(database.to_string(), table_name.to_string()))?;
+        self.is_fresh(*fetched).then(|| schema.clone())
+    }
+
+    pub fn store_schema(&self, database: &str, table_name: &str, schema: &Value) {
+        if !self.ttl.is_zero() {
+            self.schemas
+                .lock()
+                .unwrap()
+                .insert((database.to_string(), table_name.to_string()), (Instant::now(), schema.clone()));
+        }
+    }
+
+    /// Returns the cached table names of a database if they have not expired.
+    pub fn table_names(&self, database: &str) -> Option<Vec<String>> {
+        let table_names = self.table_names.lock().unwrap();
+        match table_names.get(database)? {
+            (Some(fetched), names) if self.is_fresh(*fetched) => Some(names.clone()),
+            _ => None,
+        }
+    }
+
+    /// Stores freshly read table names, returning true if they differ from the previous list.
+    /// The first list read for a database is not a change.
+    pub fn store_table_names(&self, database: &str, names: &[String]) -> bool {
+        let mut table_names = self.table_names.lock().unwrap();
+        let previous = table_names.insert(database.to_string(), (Some(Instant::now()), names.to_vec()));
+        previous.is_some_and(|(_, previous)| previous != names)
+    }
+
+    /// Drops every cached schema after a statement that may have changed them.
+    pub fn invalidate(&self) {
+        self.schemas.lock().unwrap().clear();
+        for (fetched, _) in self.table_names.lock().unwrap().values_mut() {
+            *fetched = None;
+        }
+    }
+
+    fn is_fresh(&self, fetched: Instant) -> bool {
+        fetched.elapsed() < self.ttl
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn store_table_names_reports_changed_lists() {
+        let cache = SchemaCache::new(Duration::from_secs(60));
+        assert!(!cache.store_table_names("shop", &names(&["orders", "users"])));
+        assert!(!cache.store_table_names("shop", &names(&["orders", "users"])));
+        assert!(cache.store_table_names("shop", &names(&["orders"])));
+        assert!(!cache.store_table_names("crm", &names(&["orders"])));
+        assert_eq!(cache.table_names("shop"), Some(names(&["orders"])));
+    }
+
+    #[test]
+    fn invalidate_keeps_table_names_for_comparison() {
+        let cache = SchemaCache::new(Duration::from_secs(60));
+        cache.store_table_names("shop", &names(&["orders"]));
+        cache.store_schema("shop", "orders", &Value::Null);
+        assert_eq!(cache.schema("shop", "orders"), Some(Value::Null));
+
+        cache.invalidate();
+        assert_eq!(cache.schema("shop", "orders"), None);
+        assert_eq!(cache.table_names("shop"), None);
+        assert!(cache.store_table_names("shop", &names(&["orders", "users"])));
+    }
+
+    #[test]
+    fn zero_ttl_disables_caching() {
+        let cache = SchemaCache::new(Duration::ZERO);
+        cache.store_schema("shop", "orders", &Value::Null);
+        cache.store_table_names("shop", &names(&["orders"]));
+        assert_eq!(cache.schema("shop", "orders"), None);
+        assert_eq!(cache.table_names("shop"), None);
+    }
+}
@@ -0,0 +1,241 @@
+// DDL export through `SHOW CREATE ...`.
+//
+// The server's own CREATE statements keep everything the structured schema leaves out:
+// partitioning, table options, charsets, column collations and routine bodies. A whole
+// database is exported in an order it can be replayed in: tables with foreign key parents
+// first, then functions and procedures, then views (which may call functions or select
+// from other views) and finally triggers.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use serde_json::{json, Value};
+use sqlx::mysql::MySqlRow;
+use sqlx::{MySql, Pool, Row};
+
+use crate::{quote_identifier, RequestContext, SchemaError};
+
+pub const KINDS: &[&str] = &["table", "view", "procedure", "function", "trigger"];
+
+/// Returns the `SHOW CREATE` statement of one object, or `None` if it is not visible to the
+/// current user (routine and trigger bodies require ownership or extra privileges).
+pub async fn show_create(
+    pool: &Pool<MySql>,
+    database: &str,
+    kind: &str,
+    name: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    // SHOW CREATE TRIGGER cannot be prepared, so use the text protocol throughout
+    let statement = format!(
+        "SHOW CREATE {} {}.{}",
+        kind.to_uppercase(),
+        quote_identifier(database),
+        quote_identifier(name)
+    );
+    let row = sqlx::raw_sql(&statement).fetch_one(pool).await?;
+    let column = match kind {
+        "table" => "Create Table",
+        "view" => "Create View",
+        "procedure" => "Create Procedure",
+        "function" => "Create Function",
+        _ => "SQL Original Statement",
+    };
+    Ok(text_column(&row, column))
+}
+
+/// Exports every table, routine, view and trigger of `database` in dependency order, as
+/// `{kind, name, ddl}` objects.
+pub async fn export_database(
+    pool: &Pool<MySql>,
+    database: &str,
+    ctx: &RequestContext,
+) -> Result<Vec<Value>, SchemaError> {
+    let mut objects: Vec<(&str, String)> = Vec::new();
+    objects.extend(table_order(pool, database).await?.into_iter().map(|name| ("table", name)));
+
+    let routines = sqlx::query(
+        "SELECT routine_type AS routine_type, routine_name AS routine_name
+         FROM information_schema.routines
+         WHERE routine_schema = ?
+         ORDER BY routine_type, routine_name",
+    )
+    .bind(database)
+    .fetch_all(pool)
+    .await?;
+    for row in routines {
+        let kind = match row.try_get::<String, _>("routine_type").unwrap_or_default().as_str() {
+            "FUNCTION" => "function",
+            _ => "procedure",
+        };
+        objects.push((kind, row.try_get::<String, _>("routine_name").unwrap_or_default()));
+    }
+
+    objects.extend(view_order(pool, database).await?.into_iter().map(|name| ("view", name)));
+
+    let triggers: Vec<String> = sqlx::query_scalar(
+        "SELECT trigger_name AS trigger_name FROM information_schema.triggers
+         WHERE trigger_schema = ?
+         ORDER BY event_object_table, action_timing, event_manipulation, action_order",
+    )
+    .bind(database)
+    .fetch_all(pool)
+    .await?;
+    objects.extend(triggers.into_iter().map(|name| ("trigger", name)));
+
+    let mut exported = Vec::new();
+    let total = objects.len();
+    for (done, (kind, name)) in objects.into_iter().enumerate() {
+        if ctx.is_cancelled() {
+            return Err(SchemaError::Cancelled);
+        }
+        ctx.report_progress(done, total, &format!("Reading DDL of {kind} '{name}'"));
+        let ddl = show_create(pool, database, kind, &name).await?;
+        exported.push(json!({ "kind": kind, "name": name, "ddl": ddl }));
+    }
+    Ok(exported)
+}
+
+// Base tables with every table after the tables its foreign keys reference
+async fn table_order(pool: &Pool<MySql>, database: &str) -> Result<Vec<String>, sqlx::Error> {
+    let tables: Vec<String> = sqlx::query_scalar(
+        "SELECT table_name AS table_name FROM information_schema.tables
+         WHERE table_schema = ? AND table_type = 'BASE TABLE'
+         ORDER BY table_name",
+    )
+    .bind(database)
+    .fetch_all(pool)
+    .await?;
+
+    let references = sqlx::query(
+        "SELECT table_name AS table_name, referenced_table_name AS referenced_table_name
+         FROM information_schema.referential_constraints
+         WHERE constraint_schema = ? AND unique_constraint_schema = ?",
+    )
+    .bind(database)
+    .bind(database)
+    .fetch_all(pool)
+    .await?;
+    let mut dependencies: HashMap<String, HashSet<String>> = HashMap::new();
+    for row in references {
+        dependencies
+            .entry(row.try_get::<String, _>("table_name").unwrap_or_default())
+            .or_default()
+            .insert(row.try_get::<String, _>("referenced_table_name").unwrap_or_default());
+    }
+
+    Ok(dependency_order(tables, &dependencies))
+}
+
+// Views with every view after the views it selects from
+async fn view_order(pool: &Pool<MySql>, database: &str) -> Result<Vec<String>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT table_name AS table_name, view_definition AS view_definition
+         FROM information_schema.views
+         WHERE table_schema = ?
+         ORDER BY table_name",
+    )
+    .bind(database)
+    .fetch_all(pool)
+    .await?;
+    let views: Vec<(String, String)> = rows
+        .into_iter()
+        .map(|row| {
+            (
+                row.try_get::<String, _>("table_name").unwrap_or_default(),
+                row.try_get::<Option<String>, _>("view_definition").unwrap_or_default().unwrap_or_default(),
+            )
+        })
+        .collect();
+
+    // VIEW_TABLE_USAGE only exists on MySQL 8.0.13+, but stored view definitions always quote
+    // identifiers, so look for the other views' quoted names instead
+    let mut dependencies: HashMap<String, HashSet<String>> = HashMap::new();
+    for (name, definition) in &views {
+        for (other, _) in &views {
+            if other != name && definition.contains(&quote_identifier(other)) {
+                dependencies.entry(name.clone()).or_default().insert(other.clone());
+            }
+        }
+    }
+
+    Ok(dependency_order(views.into_iter().map(|(name, _)| name).collect(), &dependencies))
+}
+
+// Orders `names` so each one comes after its dependencies, keeping alphabetical order where
+// there is a choice. Cycles, which foreign keys allow, are broken at the first remaining name.
+fn dependency_order(names: Vec<String>, dependencies: &HashMap<String, HashSet<String>>) -> Vec<String> {
+    let mut remaining: BTreeSet<String> = names.into_iter().collect();
+    let mut ordered = Vec::with_capacity(remaining.len());
+    while !remaining.is_empty() {
+        let next = remaining
+            .iter()
+            .find(|name| {
+                dependencies.get(*name).is_none_or(|deps| {
+                    deps.iter().all(|dep| dep == *name || !remaining.contains(dep))
+                })
+            })
+            .or_else(|| remaining.first())
+            .cloned()
+            .unwrap_or_default();
+        remaining.remove(&next);
+        ordered.push(next);
+    }
+    ordered
+}
+
+// SHOW CREATE columns are reported as binary by some servers, so accept either
+fn text_column(row: &MySqlRow, column: &str) -> Option<String> {
+    row.try_get::<Option<String>, _>(column).ok().flatten().or_else(|| {
+        row.try_get::<Option<Vec<u8>>, _>(column)
+            .ok()
+            .flatten()
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dependencies(edges: &[(&str, &str)]) -> HashMap<String, HashSet<String>> {
+        let mut dependencies: HashMap<String, HashSet<String>> = HashMap::new();
+        for (name, dependency) in edges {
+            dependencies.entry(name.to_string()).or_default().insert(dependency.to_string());
+        }
+        dependencies
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn dependencies_come_first() {
+        let order = dependency_order(
+            names(&["order_items", "orders", "products", "users"]),
+            &dependencies(&[("order_items", "orders"), ("order_items", "products"), ("orders", "users")]),
+        );
+        assert_eq!(order, names(&["products", "users", "orders", "order_items"]));
+    }
+
+    #[test]
+    fn independent_names_stay_alphabetical() {
+        let order = dependency_order(names(&["c", "a", "b"]), &HashMap::new());
+        assert_eq!(order, names(&["a", "b", "c"]));
+    }
+
+    #[test]
+    fn self_references_and_cycles_are_broken() {
+        let order = dependency_order(
+            names(&["a", "b", "employees"]),
+            &dependencies(&[("employees", "employees"), ("a", "b"), ("b", "a")]),
+        );
+        // Self-references never block, a cycle is only broken once nothing else is ready
+        assert_eq!(order, names(&["employees", "a", "b"]));
+    }
+
+    #[test]
+    fn dependencies_outside_the_list_are_ignored() {
+        let order = dependency_order(names(&["b", "a"]), &dependencies(&[("a", "elsewhere")]));
+        assert_eq!(order, names(&["a", "b"]));
+    }
+}
@@ -0,0 +1,183 @@
+// Column-type-driven conversion of query result rows to JSON.
+//
+// Each value is decoded according to the column's `MySqlTypeInfo` so nothing is
+// silently turned into `null`: decimals keep every digit as a string, temporal
+// types become ISO-8601 strings, binary data is base64 encoded and JSON columns
+// are embedded as JSON documents.
+
+use base64::Engine;
+use log::{debug, warn};
+use serde_json::{json, Map, Value};
+use sqlx::mysql::types::MySqlTime;
+use sqlx::mysql::{MySql, MySqlRow};
+use sqlx::types::chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use sqlx::{Column, Decode, Row, TypeInfo, ValueRef};
+
+/// Converts a result row into a JSON object keyed by column name.
+pub fn row_to_json(row: &MySqlRow) -> Map<String, Value> {
+    row.columns()
+        .iter()
+        .map(|column| (column.name().to_string(), column_to_json(row, column.ordinal(), column.type_info().name())))
+        .collect()
+}
+
+fn column_to_json(row: &MySqlRow, index: usize, type_name: &str) -> Value {
+    match row.try_get_raw(index) {
+        Ok(raw) if raw.is_null() => return Value::Null,
+        Ok(_) => {}
+        Err(e) => {
+            debug!("Failed to read column {index}: {e}");
+            return Value::Null;
+        }
+    }
+
+    let value = match decoding(type_name) {
+        Decoding::Signed => decode::<i64>(row, index).map(|v| json!(v)),
+        Decoding::Unsigned => decode::<u64>(row, index).map(|v| json!(v)),
+        // Go through the shortest decimal representation so 0.1 stays 0.1 rather than 0.10000000149011612
+        Decoding::Float => decode::<f32>(row, index).and_then(|v| v.to_string().parse::<f64>().ok()).map(|v| json!(v)),
+        Decoding::Double => decode::<f64>(row, index).map(|v| json!(v)),
+        // MySQL sends DECIMAL as text, keep it as-is to avoid losing precision
+        Decoding::Decimal => decode::<String>(row, index).map(Value::String),
+        Decoding::Date => decode::<NaiveDate>(row, index)
+            .map(|v| v.to_string())
+            .or_else(|| zero_date(row, index, "0000-00-00"))
+            .map(Value::String),
+        Decoding::DateTime => decode::<NaiveDateTime>(row, index)
+            .map(|v| v.format("%Y-%m-%dT%H:%M:%S%.f").to_string())
+            .or_else(|| zero_date(row, index, "0000-00-00T00:00:00"))
+            .map(Value::String),
+        // sqlx sets the session time zone to UTC, so TIMESTAMP values are reported in UTC
+        Decoding::Timestamp => decode::<DateTime<Utc>>(row, index)
+            .map(|v| v.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string())
+            .or_else(|| zero_date(row, index, "0000-00-00T00:00:00Z"))
+            .map(Value::String),
+        Decoding::Time => decode::<MySqlTime>(row, index).map(|v| Value::String(format_time(&v))),
+        Decoding::Json => decode::<String>(row, index).and_then(|v| serde_json::from_str(&v).ok()),
+        Decoding::Binary => decode::<Vec<u8>>(row, index).map(|v| base64_value(&v)),
+        // Fall back to base64 for non-UTF-8 data
+        Decoding::Text => decode::<String>(row, index)
+            .map(Value::String)
+            .or_else(|| decode::<Vec<u8>>(row, index).map(|v| base64_value(&v))),
+    };
+
+    value.unwrap_or_else(|| {
+        warn!("Failed to decode column {index} of type {type_name}");
+        Value::Null
+    })
+}
+
+// How a value is decoded, by the name of its column type
+#[derive(Debug, PartialEq)]
+enum Decoding {
+    Signed,
+    Unsigned,
+    Float,
+    Double,
+    Decimal,
+    Date,
+    DateTime,
+    Timestamp,
+    Time,
+    Json,
+    Binary,
+    Text,
+}
+
+fn decoding(type_name: &str) -> Decoding {
+    match type_name {
+        "BOOLEAN" | "TINYINT" | "SMALLINT" | "MEDIUMINT" | "INT" | "BIGINT" => Decoding::Signed,
+        "TINYINT UNSIGNED" | "SMALLINT UNSIGNED" | "MEDIUMINT UNSIGNED" | "INT UNSIGNED" | "BIGINT UNSIGNED"
+        | "YEAR" | "BIT" => Decoding::Unsigned,
+        "FLOAT" => Decoding::Float,
+        "DOUBLE" => Decoding::Double,
+        "DECIMAL" => Decoding::Decimal,
+        "DATE" => Decoding::Date,
+        "DATETIME" => Decoding::DateTime,
+        "TIMESTAMP" => Decoding::Timestamp,
+        "TIME" => Decoding::Time,
+        "JSON" => Decoding::Json,
+        "BINARY" | "VARBINARY" | "TINYBLOB" | "BLOB" | "MEDIUMBLOB" | "LONGBLOB" | "GEOMETRY" => Decoding::Binary,
+        // CHAR, VARCHAR, TEXT, ENUM, SET and anything newer
+        _ => Decoding::Text,
+    }
+}
+
+// chrono has no zero date, so `zero` stands in for MySQL's. Any other value that failed to
+// decode is left for the caller to report as null.
+fn zero_date(row: &MySqlRow, index: usize, zero: &str) -> Option<String> {
+    decode::<&[u8]>(row, index).filter(|bytes| is_zero_date(bytes)).map(|_| zero.to_string())
+}
+
+// '0000-00-00 00:00:00' in the text protocol; in the binary protocol a length byte followed by
+// zero fields, usually none at all
+fn is_zero_date(bytes: &[u8]) -> bool {
+    let text_zero = !bytes.is_empty() && bytes.iter().all(|b| matches!(b, b'0' | b'-' | b':' | b' ' | b'.'));
+    let binary_zero = bytes.split_first().is_some_and(|(_, fields)| fields.iter().all(|b| *b == 0));
+    text_zero || binary_zero
+}
+
+// The column type has already been matched, so skip sqlx's compatibility check
+fn decode<'r, T: Decode<'r, MySql>>(row: &'r MySqlRow, index: usize) -> Option<T> {
+    row.try_get_unchecked::<T, _>(index).ok()
+}
+
+fn base64_value(bytes: &[u8]) -> Value {
+    json!({ "base64": base64::engine::general_purpose::STANDARD.encode(bytes) })
+}
+
+// TIME is a signed interval of up to 838 hours, not just a time of day
+fn format_time(time: &MySqlTime) -> String {
+    // `MySqlTime::is_negative` reports the opposite sign in sqlx 0.8, ask the sign itself
+    let sign = if time.sign().is_negative() { "-" } else { "" };
+    let mut formatted = format!("{sign}{:02}:{:02}:{:02}", time.hours(), time.minutes(), time.seconds());
+    if time.microseconds() != 0 {
+        formatted.push_str(&format!(".{:06}", time.microseconds()));
+    }
+    formatted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::mysql::types::MySqlTimeSign;
+
+    #[test]
+    fn encodes_bytes_as_base64() {
+        assert_eq!(base64_value(b"\x00\xffhi"), json!({ "base64": "AP9oaQ==" }));
+        assert_eq!(base64_value(b""), json!({ "base64": "" }));
+    }
+
+    #[test]
+    fn maps_column_types() {
+        assert_eq!(decoding("DECIMAL"), Decoding::Decimal);
+        assert_eq!(decoding("BIT"), Decoding::Unsigned);
+        assert_eq!(decoding("YEAR"), Decoding::Unsigned);
+        assert_eq!(decoding("BIGINT UNSIGNED"), Decoding::Unsigned);
+        assert_eq!(decoding("TINYINT"), Decoding::Signed);
+        assert_eq!(decoding("GEOMETRY"), Decoding::Binary);
+        assert_eq!(decoding("ENUM"), Decoding::Text);
+    }
+
+    #[test]
+    fn recognises_only_zero_dates() {
+        assert!(is_zero_date(b"0000-00-00"));
+        assert!(is_zero_date(b"0000-00-00 00:00:00.000000"));
+        assert!(is_zero_date(&[0]));
+        assert!(is_zero_date(&[4, 0, 0, 0, 0]));
+        assert!(!is_zero_date(b""));
+        assert!(!is_zero_date(b"2024-02-30"));
+        assert!(!is_zero_date(&[4, 0xE8, 0x07, 2, 30]));
+    }
+
+    #[test]
+    fn formats_signed_intervals() {
+        let time = |sign, hours, minutes, seconds, microseconds| {
+            format_time(&MySqlTime::new(sign, hours, minutes, seconds, microseconds).unwrap())
+        };
+        assert_eq!(time(MySqlTimeSign::Positive, 9, 5, 3, 0), "09:05:03");
+        assert_eq!(time(MySqlTimeSign::Negative, 838, 59, 59, 0), "-838:59:59");
+        assert_eq!(time(MySqlTimeSign::Positive, 0, 0, 1, 250), "00:00:01.000250");
+        assert_eq!(time(MySqlTimeSign::Negative, 1, 2, 3, 500_000), "-01:02:03.500000");
+    }
+}
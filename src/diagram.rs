@@ -0,0 +1,316 @@
+// Entity-relationship diagrams rendered from table schemas.
+//
+// Tables are read through the schema cache, either the whole database or a set of tables plus
+// the tables within a number of foreign key hops, and rendered as a Mermaid `erDiagram` or a
+// Graphviz DOT graph. Only foreign keys between tables in the diagram are drawn.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_json::Value;
+use sqlx::{MySql, Pool};
+
+use crate::{cached_table_schema, RequestContext, SchemaError};
+
+pub const FORMATS: &[&str] = &["mermaid", "dot"];
+
+/// Reads the schemas of `tables` and of every table up to `hops` foreign keys away from them,
+/// in either direction, ordered by table name.
+pub async fn neighbourhood(
+    pool: &Pool<MySql>,
+    database: &str,
+    tables: &[String],
+    hops: usize,
+    ctx: &RequestContext,
+) -> Result<Vec<Value>, SchemaError> {
+    let mut schemas: BTreeMap<String, Value> = BTreeMap::new();
+    let mut frontier: BTreeSet<String> = tables.iter().cloned().collect();
+    for hop in 0..=hops {
+        let mut next = BTreeSet::new();
+        for table_name in frontier {
+            if ctx.is_cancelled() {
+                return Ok(schemas.into_values().collect());
+            }
+            if schemas.contains_key(&table_name) {
+                continue;
+            }
+            let schema = cached_table_schema(pool, database, &table_name, ctx).await?;
+            if hop < hops {
+                next.extend(related_tables(&schema, database));
+            }
+            schemas.insert(table_name, schema);
+        }
+        frontier = next;
+    }
+    Ok(schemas.into_values().collect())
+}
+
+// Tables in `database` this table references or is referenced by
+fn related_tables(schema: &Value, database: &str) -> Vec<String> {
+    let outgoing = foreign_keys(schema, "foreign_keys")
+        .filter(|fk| fk["referenced_schema"] == database)
+        .filter_map(|fk| fk["referenced_table"].as_str());
+    let incoming = foreign_keys(schema, "referenced_by")
+        .filter(|fk| fk["schema"] == database)
+        .filter_map(|fk| fk["table"].as_str());
+    outgoing.chain(incoming).map(str::to_string).collect()
+}
+
+/// Renders a Mermaid `erDiagram` of `schemas`.
+pub fn mermaid(schemas: &[Value]) -> String {
+    let names = table_names(schemas);
+    let mut lines = vec!["erDiagram".to_string()];
+
+    for schema in schemas {
+        lines.push(format!("    {} {{", mermaid_name(table_name(schema))));
+        for column in columns(schema) {
+            let keys = column_keys(schema, column).join(", ");
+            let mut line = format!(
+                "        {} {}",
+                mermaid_name(column["type"].as_str().unwrap_or("unknown")),
+                mermaid_name(column["name"].as_str().unwrap_or_default())
+            );
+            if !keys.is_empty() {
+                line.push_str(&format!(" {keys}"));
+            }
+            if let Some(comment) = column["comment"].as_str().filter(|c| !c.is_empty()) {
+                line.push_str(&format!(" \"{}\"", comment.replace('"', "'")));
+            }
+            lines.push(line);
+        }
+        lines.push("    }".to_string());
+    }
+
+    for (schema, fk) in relationships(schemas, &names) {
+        // Child side: many rows unless the key is unique; parent side: optional if nullable
+        let child = if is_unique(schema, fk) { "|o" } else { "}o" };
+        let parent = if is_nullable(schema, fk) { "o|" } else { "||" };
+        lines.push(format!(
+            "    {} {child}--{parent} {} : \"{}\"",
+            mermaid_name(table_name(schema)),
+            mermaid_name(fk["referenced_table"].as_str().unwrap_or_default()),
+            fk["name"].as_str().unwrap_or_default().replace('"', "'")
+        ));
+    }
+
+    lines.join("\n")
+}
+
+/// Renders a Graphviz DOT graph of `schemas`, one HTML-like table node per table with an edge
+/// from each foreign key column to the column it references.
+pub fn dot(schemas: &[Value]) -> String {
+    let names = table_names(schemas);
+    let mut lines = vec![
+        "digraph er {".to_string(),
+        "    rankdir=LR;".to_string(),
+        "    node [shape=plaintext];".to_string(),
+    ];
+
+    for schema in schemas {
+        let name = table_name(schema);
+        let mut label = format!(
+            "<table border=\"0\" cellborder=\"1\" cellspacing=\"0\"><tr><td bgcolor=\"lightgrey\"><b>{}</b></td></tr>",
+            html_escape(name)
+        );
+        for column in columns(schema) {
+            let column_name = column["name"].as_str().unwrap_or_default();
+            let keys = column_keys(schema, column).join(", ");
+            label.push_str(&format!(
+                "<tr><td port=\"{}\" align=\"left\">{} <i>{}</i>{}</td></tr>",
+                html_escape(column_name),
+                html_escape(column_name),
+                html_escape(column["column_type"].as_str().unwrap_or_default()),
+                if keys.is_empty() { String::new() } else { format!(" {keys}") }
+            ));
+        }
+        label.push_str("</table>");
+        lines.push(format!("    {} [label=<{label}>];", dot_id(name)));
+    }
+
+    for (schema, fk) in relationships(schemas, &names) {
+        let referenced_columns = fk["referenced_columns"].as_array().cloned().unwrap_or_default();
+        for (column, referenced) in fk["columns"].as_array().into_iter().flatten().zip(referenced_columns) {
+            lines.push(format!(
+                "    {}:{} -> {}:{} [label={}];",
+                dot_id(table_name(schema)),
+                dot_id(column.as_str().unwrap_or_default()),
+                dot_id(fk["referenced_table"].as_str().unwrap_or_default()),
+                dot_id(referenced.as_str().unwrap_or_default()),
+                dot_id(fk["name"].as_str().unwrap_or_default())
+            ));
+        }
+    }
+
+    lines.push("}".to_string());
+    lines.join("\n")
+}
+
+fn table_name(schema: &Value) -> &str {
+    schema["table_name"].as_str().unwrap_or_default()
+}
+
+fn table_names(schemas: &[Value]) -> BTreeSet<&str> {
+    schemas.iter().map(table_name).collect()
+}
+
+fn columns(schema: &Value) -> impl Iterator<Item = &Value> {
+    schema["columns"].as_array().into_iter().flatten()
+}
+
+fn foreign_keys<'a>(schema: &'a Value, field: &str) -> impl Iterator<Item = &'a Value> {
+    schema[field].as_array().into_iter().flatten()
+}
+
+// Foreign keys whose referenced table is part of the diagram
+fn relationships<'a>(schemas: &'a [Value], names: &BTreeSet<&str>) -> Vec<(&'a Value, &'a Value)> {
+    schemas
+        .iter()
+        .flat_map(|schema| {
+            foreign_keys(schema, "foreign_keys")
+                .filter(|fk| fk["referenced_schema"] == schema["database"])
+                .filter(|fk| fk["referenced_table"].as_str().is_some_and(|table| names.contains(table)))
+                .map(move |fk| (schema, fk))
+        })
+        .collect()
+}
+
+// PK, FK and UK markers of a column
+fn column_keys(schema: &Value, column: &Value) -> Vec<&'static str> {
+    let mut keys = Vec::new();
+    match column["key"].as_str() {
+        Some("PRI") => keys.push("PK"),
+        Some("UNI") => keys.push("UK"),
+        _ => {}
+    }
+    if foreign_keys(schema, "foreign_keys").any(|fk| fk_columns(fk).any(|c| *c == column["name"])) {
+        keys.push("FK");
+    }
+    keys
+}
+
+fn fk_columns(fk: &Value) -> impl Iterator<Item = &Value> {
+    fk["columns"].as_array().into_iter().flatten()
+}
+
+fn fk_column_info<'a>(schema: &'a Value, fk: &'a Value) -> impl Iterator<Item = &'a Value> {
+    columns(schema).filter(move |column| fk_columns(fk).any(|c| *c == column["name"]))
+}
+
+fn is_nullable(schema: &Value, fk: &Value) -> bool {
+    fk_column_info(schema, fk).any(|column| column["nullable"] == true)
+}
+
+// A single-column key that is itself unique makes a one-to-one relationship
+fn is_unique(schema: &Value, fk: &Value) -> bool {
+    fk_columns(fk).count() == 1
+        && fk_column_info(schema, fk).any(|column| matches!(column["key"].as_str(), Some("PRI" | "UNI")))
+}
+
+// Mermaid names only allow letters, digits, `_` and `-`
+fn mermaid_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .collect();
+    if name.starts_with(|c: char| c.is_ascii_digit()) || name.is_empty() {
+        format!("_{name}")
+    } else {
+        name
+    }
+}
+
+fn dot_id(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schemas() -> Vec<Value> {
+        vec![
+            json!({
+                "database": "shop",
+                "table_name": "orders",
+                "columns": [
+                    {"name": "id", "type": "int", "column_type": "int", "key": "PRI", "nullable": false},
+                    {"name": "user_id", "type": "int", "column_type": "int", "key": "MUL", "nullable": false},
+                    {"name": "coupon_id", "type": "int", "column_type": "int", "key": "UNI", "nullable": true,
+                     "comment": "Applied \"coupon\""},
+                ],
+                "foreign_keys": [
+                    {"name": "fk_user", "columns": ["user_id"], "referenced_schema": "shop",
+                     "referenced_table": "users", "referenced_columns": ["id"]},
+                    {"name": "fk_coupon", "columns": ["coupon_id"], "referenced_schema": "shop",
+                     "referenced_table": "coupons", "referenced_columns": ["id"]},
+                    {"name": "fk_missing", "columns": ["user_id"], "referenced_schema": "crm",
+                     "referenced_table": "users", "referenced_columns": ["id"]},
+                ],
+            }),
+            json!({
+                "database": "shop",
+                "table_name": "users",
+                "columns": [{"name": "id", "type": "int", "column_type": "int", "key": "PRI", "nullable": false}],
+            }),
+            json!({
+                "database": "shop",
+                "table_name": "coupons",
+                "columns": [{"name": "id", "type": "int", "column_type": "int", "key": "PRI", "nullable": false}],
+            }),
+        ]
+    }
+
+    #[test]
+    fn mermaid_marks_keys_and_cardinality() {
+        let diagram = mermaid(&schemas());
+        assert!(diagram.starts_with("erDiagram\n    orders {"));
+        assert!(diagram.contains("        int id PK\n"));
+        assert!(diagram.contains("        int user_id FK\n"));
+        assert!(diagram.contains("        int coupon_id UK, FK \"Applied 'coupon'\"\n"));
+        assert!(diagram.contains("    orders }o--|| users : \"fk_user\""));
+        assert!(diagram.contains("    orders |o--o| coupons : \"fk_coupon\""));
+        // Only relationships between tables of the same database in the diagram are drawn
+        assert_eq!(diagram.matches("--").count(), 2);
+    }
+
+    #[test]
+    fn mermaid_sanitizes_names() {
+        assert_eq!(mermaid_name("order items"), "order_items");
+        assert_eq!(mermaid_name("2024_sales"), "_2024_sales");
+        assert_eq!(mermaid_name("decimal(10,2)"), "decimal_10_2_");
+        assert_eq!(mermaid_name(""), "_");
+    }
+
+    #[test]
+    fn dot_draws_column_edges() {
+        let diagram = dot(&schemas());
+        assert!(diagram.starts_with("digraph er {\n    rankdir=LR;"));
+        assert!(diagram.contains("    \"orders\":\"user_id\" -> \"users\":\"id\" [label=\"fk_user\"];"));
+        assert!(diagram.contains("    \"orders\":\"coupon_id\" -> \"coupons\":\"id\" [label=\"fk_coupon\"];"));
+        assert!(!diagram.contains("fk_missing"));
+        assert!(diagram.ends_with("\n}"));
+    }
+
+    #[test]
+    fn dot_escapes_identifiers_and_labels() {
+        assert_eq!(dot_id("say \"hi\""), "\"say \\\"hi\\\"\"");
+        assert_eq!(dot_id("a\\b"), "\"a\\\\b\"");
+        assert_eq!(html_escape("<a & \"b\">"), "&lt;a &amp; &quot;b&quot;&gt;");
+
+        let schema = json!({
+            "database": "shop",
+            "table_name": "a<b>",
+            "columns": [{"name": "x&y", "column_type": "enum('a','b')"}],
+        });
+        let diagram = dot(&[schema]);
+        assert!(diagram.contains("<b>a&lt;b&gt;</b>"));
+        assert!(diagram.contains("<td port=\"x&amp;y\" align=\"left\">x&amp;y <i>enum('a','b')</i></td>"));
+    }
+}
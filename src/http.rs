@@ -0,0 +1,346 @@
+// MCP Streamable HTTP transport.
+//
+// A single `/mcp` endpoint takes JSON-RPC messages by POST and answers either with a JSON
+// body or, when the client accepts it, an SSE stream carrying progress notifications followed
+// by the response. GET opens an SSE stream for server-initiated messages and DELETE ends the
+// session. Each session is identified by the `Mcp-Session-Id` header, so several editors can
+// share one long-lived server. Sessions connecting to the same database share one pool, and
+// sessions left without requests for `--session-idle-timeout` seconds are dropped.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE, ORIGIN};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::Router;
+use futures_util::Stream;
+use log::{debug, info, warn};
+use serde_json::Value;
+use sqlx::{MySql, Pool};
+use tokio::sync::{mpsc, Semaphore};
+
+use crate::{create_error_response, Args, Dispatcher, JsonRpcRequest, SharedPools};
+
+const SESSION_HEADER: &str = "mcp-session-id";
+
+type Sessions = Arc<Mutex<HashMap<String, Arc<Session>>>>;
+
+struct Session {
+    dispatcher: Dispatcher,
+    pool: Mutex<Option<Pool<MySql>>>,
+    // Sender of the client's GET event stream, if one is open
+    stream: Mutex<Option<mpsc::UnboundedSender<String>>>,
+    last_used: Mutex<Instant>,
+}
+
+impl Session {
+    fn touch(&self) {
+        *self.last_used.lock().unwrap() = Instant::now();
+    }
+
+    fn has_open_stream(&self) -> bool {
+        self.stream.lock().unwrap().as_ref().is_some_and(|stream| !stream.is_closed())
+    }
+}
+
+#[derive(Clone)]
+struct HttpState {
+    args: Arc<Args>,
+    limiter: Arc<Semaphore>,
+    sessions: Sessions,
+    pools: SharedPools,
+}
+
+pub async fn serve(args: Args) -> Result<(), Box<dyn std::error::Error>> {
+    let listen = args.listen;
+    // Anyone who can reach the server can query the database as the configured user
+    if !listen.ip().is_loopback() && args.auth_token.is_none() {
+        return Err(format!("Refusing to listen on non-loopback address {listen} without --auth-token").into());
+    }
+    let idle_timeout = Duration::from_secs(args.session_idle_timeout);
+    let state = HttpState {
+        limiter: Arc::new(Semaphore::new(args.max_concurrent_requests.max(1))),
+        args: Arc::new(args),
+        sessions: Arc::new(Mutex::new(HashMap::new())),
+        pools: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
+    };
+    if !idle_timeout.is_zero() {
+        tokio::spawn(expire_sessions(state.sessions.clone(), idle_timeout));
+    }
+    let app = Router::new()
+        .route("/mcp", post(handle_post).get(handle_get).delete(handle_delete))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(listen).await?;
+    info!("Listening for MCP Streamable HTTP on http://{listen}/mcp");
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+            info!("Received Ctrl-C, shutting down server");
+        })
+        .await?;
+    Ok(())
+}
+
+async fn handle_post(State(state): State<HttpState>, headers: HeaderMap, body: String) -> Response {
+    if let Err(status) = check_request(&state, &headers) {
+        return status.into_response();
+    }
+
+    let (messages, is_batch) = match serde_json::from_str::<Value>(&body) {
+        Ok(Value::Array(messages)) => (messages, true),
+        Ok(message) => (vec![message], false),
+        Err(e) => {
+            warn!("Failed to parse request: {e}");
+            return json_response(StatusCode::BAD_REQUEST, &create_error_response(None, -32700, "Parse error"));
+        }
+    };
+
+    // Responses to server-initiated requests carry no method; the server never sends requests
+    let mut requests = Vec::new();
+    for message in messages.into_iter().filter(|message| message.get("method").is_some()) {
+        match serde_json::from_value::<JsonRpcRequest>(message) {
+            Ok(request) => requests.push(request),
+            Err(e) => {
+                warn!("Invalid request: {e}");
+                return json_response(StatusCode::BAD_REQUEST, &create_error_response(None, -32600, "Invalid Request"));
+            }
+        }
+    }
+
+    let session_id = if requests.iter().any(|request| request.method == "initialize") {
+        let session_id = uuid::Uuid::new_v4().to_string();
+        let session = Session {
+            dispatcher: Dispatcher::new(state.args.clone(), state.limiter.clone(), Some(state.pools.clone())),
+            pool: Mutex::new(None),
+            stream: Mutex::new(None),
+            last_used: Mutex::new(Instant::now()),
+        };
+        state.sessions.lock().unwrap().insert(session_id.clone(), Arc::new(session));
+        info!("Created session {session_id}");
+        session_id
+    } else {
+        match headers.get(SESSION_HEADER).and_then(|value| value.to_str().ok()) {
+            Some(session_id) => session_id.to_string(),
+            None => return (StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id header").into_response(),
+        }
+    };
+    let Some(session) = state.sessions.lock().unwrap().get(&session_id).cloned() else {
+        return (StatusCode::NOT_FOUND, "Unknown session").into_response();
+    };
+    session.touch();
+
+    let (outgoing, outgoing_rx) = mpsc::unbounded_channel::<String>();
+    let mut has_requests = false;
+    for request in requests {
+        debug!("Parsed request: method={}, id={:?}", request.method, request.id);
+        let Some(ctx) = session.dispatcher.accept(&request, &outgoing) else {
+            continue;
+        };
+        has_requests = true;
+        let session = session.clone();
+        tokio::spawn(async move {
+            let is_initialize = request.method == "initialize";
+            let mut pool = session.pool.lock().unwrap().clone();
+            session.dispatcher.run(request, ctx, &mut pool).await;
+            if is_initialize {
+                *session.pool.lock().unwrap() = pool;
+            }
+        });
+    }
+    // The channel closes once every request of this POST has finished
+    drop(outgoing);
+
+    if !has_requests {
+        return with_session(StatusCode::ACCEPTED.into_response(), &session_id);
+    }
+
+    if accepts_event_stream(&headers) {
+        return with_session(event_stream(outgoing_rx).into_response(), &session_id);
+    }
+
+    // Plain JSON: notifications produced along the way go to the GET stream, if there is one
+    let mut outgoing_rx = outgoing_rx;
+    let mut responses = Vec::new();
+    while let Some(message) = outgoing_rx.recv().await {
+        let Ok(message) = serde_json::from_str::<Value>(&message) else {
+            continue;
+        };
+        if message.get("method").is_some() {
+            if let Some(stream) = session.stream.lock().unwrap().as_ref() {
+                let _ = stream.send(message.to_string());
+            }
+        } else {
+            responses.push(message);
+        }
+    }
+
+    let response = match (is_batch, responses.len()) {
+        // Every request was cancelled
+        (_, 0) => StatusCode::ACCEPTED.into_response(),
+        (false, _) => json_response(StatusCode::OK, &responses[0]),
+        (true, _) => json_response(StatusCode::OK, &responses),
+    };
+    with_session(response, &session_id)
+}
+
+async fn handle_get(State(state): State<HttpState>, headers: HeaderMap) -> Response {
+    if let Err(status) = check_request(&state, &headers) {
+        return status.into_response();
+    }
+    if !accepts_event_stream(&headers) {
+        return StatusCode::METHOD_NOT_ALLOWED.into_response();
+    }
+    let Some(session) = find_session(&state, &headers) else {
+        return (StatusCode::NOT_FOUND, "Unknown session").into_response();
+    };
+    session.touch();
+
+    let (sender, receiver) = mpsc::unbounded_channel::<String>();
+    *session.stream.lock().unwrap() = Some(sender);
+    event_stream(receiver).into_response()
+}
+
+async fn handle_delete(State(state): State<HttpState>, headers: HeaderMap) -> Response {
+    if let Err(status) = check_request(&state, &headers) {
+        return status.into_response();
+    }
+    // The session's pool is shared with other sessions on the same database, so it stays open
+    let session_id = headers.get(SESSION_HEADER).and_then(|value| value.to_str().ok());
+    let session = session_id.and_then(|session_id| state.sessions.lock().unwrap().remove(session_id));
+    match session {
+        Some(_) => {
+            info!("Closed session {}", session_id.unwrap_or_default());
+            StatusCode::NO_CONTENT.into_response()
+        }
+        None => (StatusCode::NOT_FOUND, "Unknown session").into_response(),
+    }
+}
+
+// Drop sessions with no running request, no open event stream and no request for `idle_timeout`
+async fn expire_sessions(sessions: Sessions, idle_timeout: Duration) {
+    let mut interval = tokio::time::interval(idle_timeout.min(Duration::from_secs(60)));
+    loop {
+        interval.tick().await;
+        sessions.lock().unwrap().retain(|session_id, session| {
+            // Running requests hold another reference to their session
+            let idle = Arc::strong_count(session) == 1
+                && !session.has_open_stream()
+                && session.last_used.lock().unwrap().elapsed() >= idle_timeout;
+            if idle {
+                info!("Expired idle session {session_id}");
+            }
+            !idle
+        });
+    }
+}
+
+fn find_session(state: &HttpState, headers: &HeaderMap) -> Option<Arc<Session>> {
+    let session_id = headers.get(SESSION_HEADER)?.to_str().ok()?;
+    state.sessions.lock().unwrap().get(session_id).cloned()
+}
+
+fn check_request(state: &HttpState, headers: &HeaderMap) -> Result<(), StatusCode> {
+    if !origin_allowed(headers) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    match &state.args.auth_token {
+        Some(token) if !bearer_token_matches(headers, token) => Err(StatusCode::UNAUTHORIZED),
+        _ => Ok(()),
+    }
+}
+
+fn bearer_token_matches(headers: &HeaderMap, token: &str) -> bool {
+    let Some(given) = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+    else {
+        return false;
+    };
+    // Compare every byte so the time taken doesn't reveal how much of the token matched
+    given.len() == token.len() && given.bytes().zip(token.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+// Browsers always send Origin; only allow local pages to guard against DNS rebinding
+fn origin_allowed(headers: &HeaderMap) -> bool {
+    let Some(origin) = headers.get(ORIGIN) else {
+        return true;
+    };
+    let Ok(origin) = origin.to_str() else {
+        return false;
+    };
+    let authority = origin.split_once("://").map_or(origin, |(_, authority)| authority);
+    let host = match authority.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next().unwrap_or_default(),
+        None => authority.split(':').next().unwrap_or_default(),
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "::1")
+}
+
+fn accepts_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/event-stream"))
+}
+
+fn event_stream(receiver: mpsc::UnboundedReceiver<String>) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = futures_util::stream::unfold(receiver, |mut receiver| async move {
+        let message = receiver.recv().await?;
+        Some((Ok(Event::default().event("message").data(message)), receiver))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+fn json_response(status: StatusCode, body: &impl serde::Serialize) -> Response {
+    let body = serde_json::to_string(body).unwrap_or_default();
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
+}
+
+fn with_session(mut response: Response, session_id: &str) -> Response {
+    if let Ok(value) = HeaderValue::from_str(session_id) {
+        response.headers_mut().insert(SESSION_HEADER, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(name: axum::http::HeaderName, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn checks_bearer_tokens() {
+        assert!(bearer_token_matches(&headers(AUTHORIZATION, "Bearer secret"), "secret"));
+        assert!(!bearer_token_matches(&headers(AUTHORIZATION, "Bearer secreT"), "secret"));
+        assert!(!bearer_token_matches(&headers(AUTHORIZATION, "Bearer secret2"), "secret"));
+        assert!(!bearer_token_matches(&headers(AUTHORIZATION, "secret"), "secret"));
+        assert!(!bearer_token_matches(&HeaderMap::new(), "secret"));
+    }
+
+    #[test]
+    fn allows_only_local_origins() {
+        assert!(origin_allowed(&HeaderMap::new()));
+        assert!(origin_allowed(&headers(ORIGIN, "http://localhost:3000")));
+        assert!(origin_allowed(&headers(ORIGIN, "http://[::1]:3000")));
+        assert!(!origin_allowed(&headers(ORIGIN, "http://localhost.example.com")));
+        assert!(!origin_allowed(&headers(ORIGIN, "https://example.com")));
+    }
+}
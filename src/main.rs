@@ -1,8 +1,31 @@
-use clap::Parser;
+mod cache;
+mod ddl;
+mod decode;
+mod diagram;
+mod http;
+mod profile;
+mod search;
+mod snapshot;
+mod sql_guard;
+
+use clap::{Parser, Subcommand, ValueEnum};
 use log::{debug, info, warn, error};
 use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
-use sqlx::{Column, MySql, Pool, Row};
+use serde_json::{json, Map, Value};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use futures_util::TryStreamExt;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, Semaphore};
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+use sqlx::mysql::{MySqlConnection, MySqlDatabaseError, MySqlRow};
+use sqlx::pool::PoolConnection;
+use sqlx::{Executor, MySql, Pool, Row};
 
 
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
@@ -30,10 +53,83 @@ struct Args {
     /// MySQL database name
     #[arg(long)]
     database: String,
+
+    /// Other databases tools may use through their `database` argument, comma separated; `*` allows all
+    #[arg(long, value_delimiter = ',')]
+    allowed_databases: Vec<String>,
     
-    /// Allow dangerous SQL keywords in queries (INSERT, UPDATE, DELETE, etc.)
+    /// Allow statements other than read-only queries (INSERT, UPDATE, DELETE, etc.)
     #[arg(long, default_value = "false")]
     allow_dangerous_queries: bool,
+
+    /// Maximum number of rows returned by a single query call
+    #[arg(long, default_value = "500", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    max_rows: usize,
+
+    /// Maximum size in bytes of the rows returned by a single query call
+    #[arg(long, default_value = "262144")]
+    max_bytes: usize,
+
+    /// Query timeout in seconds, 0 disables it
+    #[arg(long, default_value = "30")]
+    query_timeout: u64,
+
+    /// Seconds to cache introspected table schemas for, 0 disables the cache
+    #[arg(long, default_value = "60")]
+    schema_cache_ttl: u64,
+
+    /// Directory schema_diff may read snapshot files from; without it snapshots must be passed inline
+    #[arg(long)]
+    snapshot_dir: Option<PathBuf>,
+
+    /// Maximum number of rows the profile_table tool reads from a table
+    #[arg(long, default_value = "10000")]
+    profile_scan_rows: u64,
+
+    /// Maximum number of connections in the MySQL pool
+    #[arg(long, default_value = "5")]
+    max_connections: u32,
+
+    /// Maximum number of requests using the database at the same time
+    #[arg(long, default_value = "4")]
+    max_concurrent_requests: usize,
+
+    /// Transport to serve MCP over
+    #[arg(long, value_enum, default_value = "stdio")]
+    transport: Transport,
+
+    /// Address to listen on with `--transport http`
+    #[arg(long, default_value = "127.0.0.1:8000")]
+    listen: SocketAddr,
+
+    /// Bearer token HTTP clients must send in the Authorization header, required to listen on a non-loopback address
+    #[arg(long)]
+    auth_token: Option<String>,
+
+    /// Seconds after which an HTTP session without requests is closed, 0 keeps sessions until deleted
+    #[arg(long, default_value = "1800")]
+    session_idle_timeout: u64,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Write a schema snapshot of the database as JSON and exit
+    Snapshot {
+        /// File to write the snapshot to, standard output if omitted
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Transport {
+    /// Newline-delimited JSON-RPC over stdin/stdout
+    Stdio,
+    /// MCP Streamable HTTP
+    Http,
 }
 
 // JSON-RPC structures
@@ -64,6 +160,61 @@ struct JsonRpcError {
     data: Option<Value>,
 }
 
+// Cancellation tokens of requests that have been received but not answered yet
+type InFlightRequests = Arc<Mutex<HashMap<String, CancellationToken>>>;
+
+// Pools by database URL, shared by all sessions of the HTTP transport
+type SharedPools = Arc<tokio::sync::Mutex<HashMap<String, Pool<MySql>>>>;
+
+// Per-request state handed to handlers: cancellation, progress reporting and the session's schema cache
+#[derive(Clone)]
+struct RequestContext {
+    cancel: CancellationToken,
+    progress_token: Option<Value>,
+    outgoing: mpsc::UnboundedSender<String>,
+    schema_cache: Arc<cache::SchemaCache>,
+    // Set when initialize should reuse the pool of another session instead of opening its own
+    shared_pools: Option<SharedPools>,
+}
+
+impl RequestContext {
+    // The response to a cancelled request is discarded, so long-running handlers check this
+    // between statements and stop early
+    fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    // Send notifications/progress if the client asked for it with a progressToken
+    fn report_progress(&self, progress: usize, total: usize, message: &str) {
+        if let Some(progress_token) = &self.progress_token {
+            send_message(
+                &self.outgoing,
+                &json!({
+                    "jsonrpc": "2.0",
+                    "method": "notifications/progress",
+                    "params": {
+                        "progressToken": progress_token,
+                        "progress": progress,
+                        "total": total,
+                        "message": message
+                    }
+                }),
+            );
+        }
+    }
+
+    // Send notifications/resources/list_changed after the tables behind the resource list changed
+    fn notify_resource_list_changed(&self) {
+        send_message(
+            &self.outgoing,
+            &json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/resources/list_changed"
+            }),
+        );
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct InitializeParams {
@@ -102,6 +253,8 @@ struct InitializeResult {
 #[derive(Debug, Serialize)]
 struct ServerCapabilities {
     tools: Option<ToolsCapability>,
+    resources: Option<ResourcesCapability>,
+    prompts: Option<PromptsCapability>,
 }
 
 #[derive(Debug, Serialize)]
@@ -110,6 +263,13 @@ struct ToolsCapability {
     list_changed: bool,
 }
 
+#[derive(Debug, Serialize)]
+struct ResourcesCapability {
+    subscribe: bool,
+    #[serde(rename = "listChanged")]
+    list_changed: bool,
+}
+
 #[derive(Debug, Serialize)]
 struct Tool {
     name: String,
@@ -123,6 +283,73 @@ struct ToolsList {
     tools: Vec<Tool>,
 }
 
+#[derive(Debug, Serialize)]
+struct PromptsCapability {
+    #[serde(rename = "listChanged")]
+    list_changed: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct Resource {
+    uri: String,
+    name: String,
+    description: String,
+    #[serde(rename = "mimeType")]
+    mime_type: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ResourcesList {
+    resources: Vec<Resource>,
+}
+
+#[derive(Debug, Serialize)]
+struct ResourceTemplate {
+    #[serde(rename = "uriTemplate")]
+    uri_template: String,
+    name: String,
+    description: String,
+    #[serde(rename = "mimeType")]
+    mime_type: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ResourceTemplatesList {
+    #[serde(rename = "resourceTemplates")]
+    resource_templates: Vec<ResourceTemplate>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResourceReadParams {
+    uri: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Prompt {
+    name: String,
+    description: String,
+    arguments: Vec<PromptArgument>,
+}
+
+#[derive(Debug, Serialize)]
+struct PromptArgument {
+    name: String,
+    description: String,
+    required: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct PromptsList {
+    prompts: Vec<Prompt>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PromptGetParams {
+    name: String,
+    #[serde(default)]
+    arguments: std::collections::HashMap<String, String>,
+}
+
 #[derive(Debug, Deserialize)]
 struct ToolCallParams {
     name: String,
@@ -132,11 +359,176 @@ struct ToolCallParams {
 #[derive(Debug, Deserialize)]
 struct SchemaArguments {
     table_name: String,
+    #[serde(default = "default_detail")]
+    detail: String,
+    database: Option<String>,
+}
+
+fn default_detail() -> String {
+    "full".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+struct SchemaObjectsArguments {
+    kind: String,
+    name: Option<String>,
+    database: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchArguments {
+    query: String,
+    limit: Option<usize>,
+    database: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiagramArguments {
+    #[serde(default = "default_diagram_format")]
+    format: String,
+    tables: Option<Vec<String>>,
+    #[serde(default = "default_hops")]
+    hops: usize,
+    database: Option<String>,
+}
+
+fn default_diagram_format() -> String {
+    "mermaid".to_string()
+}
+
+fn default_hops() -> usize {
+    1
+}
+
+#[derive(Debug, Deserialize)]
+struct SnapshotArguments {
+    database: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiffArguments {
+    from: Value,
+    to: Option<Value>,
+    database: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DdlArguments {
+    kind: Option<String>,
+    name: Option<String>,
+    database: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProfileArguments {
+    table_name: String,
+    #[serde(default = "default_sample_rows")]
+    sample_rows: usize,
+    #[serde(default = "default_top_values")]
+    top_values: usize,
+    scan_rows: Option<u64>,
+    database: Option<String>,
+}
+
+fn default_sample_rows() -> usize {
+    5
+}
+
+fn default_top_values() -> usize {
+    5
 }
 
 #[derive(Debug, Deserialize)]
 struct QueryArguments {
+    query: Option<String>,
+    cursor: Option<String>,
+    timeout_ms: Option<u64>,
+    database: Option<String>,
+}
+
+// Position of the next page of a truncated query result, handed to the client as an opaque string
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct QueryCursor {
     query: String,
+    offset: usize,
+    // Database the query runs in when it is not the configured one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    database: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct QueryLimits {
+    max_rows: usize,
+    max_bytes: usize,
+    timeout: Option<Duration>,
+}
+
+struct QueryPage {
+    rows: Vec<Value>,
+    truncated: bool,
+}
+
+enum QueryError {
+    Database(sqlx::Error),
+    TimedOut(Duration),
+    Cancelled,
+}
+
+impl From<sqlx::Error> for QueryError {
+    fn from(error: sqlx::Error) -> Self {
+        QueryError::Database(error)
+    }
+}
+
+#[derive(Debug)]
+enum SchemaError {
+    Database(sqlx::Error),
+    TableNotFound { database: String, table_name: String },
+    TimedOut(Duration),
+    Cancelled,
+}
+
+impl From<sqlx::Error> for SchemaError {
+    fn from(error: sqlx::Error) -> Self {
+        SchemaError::Database(error)
+    }
+}
+
+impl From<QueryError> for SchemaError {
+    fn from(error: QueryError) -> Self {
+        match error {
+            QueryError::Database(e) => SchemaError::Database(e),
+            QueryError::TimedOut(timeout) => SchemaError::TimedOut(timeout),
+            QueryError::Cancelled => SchemaError::Cancelled,
+        }
+    }
+}
+
+impl std::fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchemaError::Database(e) => write!(f, "{e}"),
+            SchemaError::TableNotFound { database, table_name } => {
+                write!(f, "Table '{table_name}' does not exist in database '{database}'")
+            }
+            SchemaError::TimedOut(timeout) => write!(f, "Timed out after {}ms and was cancelled", timeout.as_millis()),
+            SchemaError::Cancelled => write!(f, "Request cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+impl SchemaError {
+    // A missing table is the caller's mistake rather than a server failure
+    fn code(&self) -> i32 {
+        match self {
+            SchemaError::Database(_) => -32603,
+            SchemaError::TableNotFound { .. } => -32602,
+            SchemaError::TimedOut(_) => -32006,
+            SchemaError::Cancelled => -32800,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -164,16 +556,6 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::init();
     
     let args = Args::parse();
-    let allow_dangerous_queries = args.allow_dangerous_queries;
-    
-    // Defer database connection until initialize request is received
-    let mut pool: Option<Pool<MySql>> = None;
-
-    // Set up stdio
-    let stdin = tokio::io::stdin();
-    let mut stdout = tokio::io::stdout();
-    let reader = BufReader::new(stdin);
-    let mut lines = reader.lines();
 
     // Send logs to stderr to avoid interfering with JSON-RPC communication
     info!("MCP MySQL Server started and ready to accept connections");
@@ -188,6 +570,102 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
     debug!("Current working directory: {:?}", std::env::current_dir());
 
+    if let Some(Command::Snapshot { output }) = &args.command {
+        return write_snapshot(&args, output.as_deref()).await;
+    }
+
+    if args.max_concurrent_requests >= args.max_connections as usize {
+        warn!(
+            "--max-concurrent-requests ({}) should be below --max-connections ({}) so a connection stays free for KILL QUERY",
+            args.max_concurrent_requests, args.max_connections
+        );
+    }
+
+    match args.transport {
+        Transport::Stdio => run_stdio(args).await?,
+        Transport::Http => http::serve(args).await?,
+    }
+
+    info!("MCP MySQL Server shutdown complete");
+    Ok(())
+}
+
+fn database_url(args: &Args) -> String {
+    format!(
+        "mysql://{}:{}@{}:{}/{}",
+        args.username, args.password, args.host, args.port, args.database
+    )
+}
+
+// `snapshot` subcommand: write a schema snapshot without serving MCP
+async fn write_snapshot(args: &Args, output: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+    let pool = connect_with_retry(&database_url(args), 1).await?;
+    let (outgoing, _) = mpsc::unbounded_channel();
+    let ctx = RequestContext {
+        cancel: CancellationToken::new(),
+        progress_token: None,
+        outgoing,
+        schema_cache: Arc::new(cache::SchemaCache::new(Duration::ZERO)),
+        shared_pools: None,
+    };
+    let snapshot = snapshot::take(&pool, &args.database, &ctx).await?;
+    pool.close().await;
+
+    let text = serde_json::to_string_pretty(&snapshot)?;
+    match output {
+        Some(path) => {
+            std::fs::write(path, text + "\n")?;
+            info!("Wrote schema snapshot to {}", path.display());
+        }
+        None => println!("{text}"),
+    }
+    Ok(())
+}
+
+async fn run_stdio(args: Args) -> Result<(), Box<dyn std::error::Error>> {
+    // Set up stdio
+    let stdin = tokio::io::stdin();
+    let reader = BufReader::new(stdin);
+    let mut lines = reader.lines();
+
+    // All output goes through a single writer task so responses and notifications don't interleave
+    let (outgoing, mut outgoing_rx) = mpsc::unbounded_channel::<String>();
+    let writer = tokio::spawn(async move {
+        let mut stdout = tokio::io::stdout();
+        while let Some(message) = outgoing_rx.recv().await {
+            if let Err(e) = write_response(&mut stdout, &message).await {
+                error!("Failed to write response: {e}");
+                // Continue processing other requests
+            }
+        }
+    });
+
+    // Requests run as independent tasks sharing the pool, while this loop keeps reading stdin
+    // so that pings and cancellations are never stuck behind a slow request
+    let limiter = Arc::new(Semaphore::new(args.max_concurrent_requests.max(1)));
+    let dispatcher = Dispatcher::new(Arc::new(args), limiter, None);
+    let (requests, mut requests_rx) = mpsc::unbounded_channel::<(JsonRpcRequest, RequestContext)>();
+    let dispatch_task = {
+        let dispatcher = dispatcher.clone();
+        tokio::spawn(async move {
+            // Defer database connection until initialize request is received
+            let mut pool: Option<Pool<MySql>> = None;
+            let mut tasks = JoinSet::new();
+            while let Some((request, ctx)) = requests_rx.recv().await {
+                while tasks.try_join_next().is_some() {}
+                if request.method == "initialize" {
+                    // initialize replaces the pool, so it runs before any later request is dispatched
+                    dispatcher.run(request, ctx, &mut pool).await;
+                } else {
+                    let dispatcher = dispatcher.clone();
+                    let mut pool = pool.clone();
+                    tasks.spawn(async move { dispatcher.run(request, ctx, &mut pool).await });
+                }
+            }
+            while tasks.join_next().await.is_some() {}
+        })
+    };
+
     // Process incoming messages with improved error handling
     loop {
         match lines.next_line().await {
@@ -201,36 +679,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 match serde_json::from_str::<JsonRpcRequest>(&line) {
                     Ok(request) => {
                         debug!("Parsed request: method={}, id={:?}", request.method, request.id);
-                        // Handle notifications (no response needed)
-                        if request.method == "notifications/initialized" || request.method == "initialized" {
-                            debug!("Received initialization notification: {}", request.method);
+                        let Some(ctx) = dispatcher.accept(&request, &outgoing) else {
                             continue;
-                        }
-                        
-                        let response = handle_request(request, &mut pool, &args, allow_dangerous_queries).await;
-                        match serde_json::to_string(&response) {
-                            Ok(response_str) => {
-                                if let Err(e) = write_response(&mut stdout, &response_str).await {
-                                    error!("Failed to write response: {e}");
-                                    // Continue processing other requests
-                                }
-                            }
-                            Err(e) => {
-                                error!("Failed to serialize response: {e}");
-                                // Send a generic error response
-                                let error_response = create_error_response(None, -32603, "Internal error");
-                                if let Ok(error_str) = serde_json::to_string(&error_response) {
-                                    let _ = write_response(&mut stdout, &error_str).await;
-                                }
-                            }
+                        };
+                        if requests.send((request, ctx)).is_err() {
+                            error!("Request dispatcher stopped, shutting down server");
+                            break;
                         }
                     }
                     Err(e) => {
                         warn!("Failed to parse request: {e}");
-                        let error_response = create_error_response(None, -32700, "Parse error");
-                        if let Ok(response_str) = serde_json::to_string(&error_response) {
-                            let _ = write_response(&mut stdout, &response_str).await;
-                        }
+                        send_message(&outgoing, &create_error_response(None, -32700, "Parse error"));
                     }
                 }
             }
@@ -252,10 +711,136 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
-    info!("MCP MySQL Server shutdown complete");
+    // Let queued requests finish and their responses be written before exiting
+    drop(requests);
+    let _ = dispatch_task.await;
+    drop(outgoing);
+    let _ = writer.await;
     Ok(())
 }
 
+// Shared state for running requests concurrently, one per client connection
+#[derive(Clone)]
+struct Dispatcher {
+    args: Arc<Args>,
+    // Bounds the number of requests using the database at once, so the pool isn't exhausted
+    limiter: Arc<Semaphore>,
+    in_flight: InFlightRequests,
+    schema_cache: Arc<cache::SchemaCache>,
+    shared_pools: Option<SharedPools>,
+}
+
+impl Dispatcher {
+    fn new(args: Arc<Args>, limiter: Arc<Semaphore>, shared_pools: Option<SharedPools>) -> Self {
+        let schema_cache = Arc::new(cache::SchemaCache::new(Duration::from_secs(args.schema_cache_ttl)));
+        Dispatcher {
+            args,
+            limiter,
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+            schema_cache,
+            shared_pools,
+        }
+    }
+
+    // Handle notifications, and register requests so they can be cancelled. Returns the
+    // context to run a request with, or None if the message needs no response.
+    fn accept(&self, request: &JsonRpcRequest, outgoing: &mpsc::UnboundedSender<String>) -> Option<RequestContext> {
+        // Handle notifications (no response needed)
+        if request.method == "notifications/initialized" || request.method == "initialized" {
+            debug!("Received initialization notification: {}", request.method);
+            return None;
+        }
+        if request.method == "notifications/cancelled" {
+            handle_cancelled(request.params.as_ref(), &self.in_flight);
+            return None;
+        }
+        if request.id.is_none() {
+            debug!("Ignoring notification: {}", request.method);
+            return None;
+        }
+
+        let ctx = RequestContext {
+            cancel: CancellationToken::new(),
+            progress_token: request
+                .params
+                .as_ref()
+                .and_then(|params| params.get("_meta"))
+                .and_then(|meta| meta.get("progressToken"))
+                .cloned(),
+            outgoing: outgoing.clone(),
+            schema_cache: self.schema_cache.clone(),
+            shared_pools: self.shared_pools.clone(),
+        };
+        self.in_flight.lock().unwrap().insert(request_key(&request.id), ctx.cancel.clone());
+        Some(ctx)
+    }
+
+    async fn run(&self, request: JsonRpcRequest, ctx: RequestContext, pool: &mut Option<Pool<MySql>>) {
+        let key = request_key(&request.id);
+        let _permit = if uses_database(&request.method) {
+            tokio::select! {
+                permit = self.limiter.clone().acquire_owned() => permit.ok(),
+                _ = ctx.cancel.cancelled() => None,
+            }
+        } else {
+            None
+        };
+
+        if !ctx.cancel.is_cancelled() {
+            // Dropping the handler aborts whatever statement it is waiting on
+            let response = tokio::select! {
+                response = handle_request(request, pool, &self.args, self.args.allow_dangerous_queries, &ctx) => Some(response),
+                _ = ctx.cancel.cancelled() => None,
+            };
+            // Cancelled requests get no response
+            if let Some(response) = response.filter(|_| !ctx.cancel.is_cancelled()) {
+                send_message(&ctx.outgoing, &response);
+            }
+        }
+        self.in_flight.lock().unwrap().remove(&key);
+    }
+}
+
+fn uses_database(method: &str) -> bool {
+    matches!(method, "tools/call" | "resources/list" | "resources/read" | "prompts/get")
+}
+
+fn send_message(outgoing: &mpsc::UnboundedSender<String>, message: &impl Serialize) {
+    match serde_json::to_string(message) {
+        Ok(message_str) => {
+            let _ = outgoing.send(message_str);
+        }
+        Err(e) => {
+            error!("Failed to serialize response: {e}");
+            // Send a generic error response
+            let error_response = create_error_response(None, -32603, "Internal error");
+            if let Ok(error_str) = serde_json::to_string(&error_response) {
+                let _ = outgoing.send(error_str);
+            }
+        }
+    }
+}
+
+// JSON-RPC ids may be numbers or strings, key requests by their JSON form
+fn request_key(id: &Option<Value>) -> String {
+    id.as_ref().map(Value::to_string).unwrap_or_default()
+}
+
+fn handle_cancelled(params: Option<&Value>, in_flight: &InFlightRequests) {
+    let request_id = params.and_then(|params| params.get("requestId")).cloned();
+    let reason = params
+        .and_then(|params| params.get("reason"))
+        .and_then(Value::as_str)
+        .unwrap_or("no reason given");
+    match in_flight.lock().unwrap().get(&request_key(&request_id)) {
+        Some(cancel) => {
+            info!("Cancelling request {request_id:?}: {reason}");
+            cancel.cancel();
+        }
+        None => debug!("Ignoring cancellation of unknown request {request_id:?}"),
+    }
+}
+
 async fn write_response(stdout: &mut tokio::io::Stdout, response: &str) -> Result<(), Box<dyn std::error::Error>> {
     stdout.write_all(response.as_bytes()).await?;
     stdout.write_all(b"\n").await?;
@@ -263,14 +848,34 @@ async fn write_response(stdout: &mut tokio::io::Stdout, response: &str) -> Resul
     Ok(())
 }
 
-async fn connect_with_retry(database_url: &str) -> Result<Pool<MySql>, Box<dyn std::error::Error>> {
+// Connect, or with shared pools reuse the open pool for the same URL
+async fn connect(
+    database_url: &str,
+    max_connections: u32,
+    shared_pools: Option<&SharedPools>,
+) -> Result<Pool<MySql>, Box<dyn std::error::Error>> {
+    let Some(shared_pools) = shared_pools else {
+        return connect_with_retry(database_url, max_connections).await;
+    };
+    // Held while connecting so sessions initializing at once don't each open a pool
+    let mut pools = shared_pools.lock().await;
+    if let Some(pool) = pools.get(database_url).filter(|pool| !pool.is_closed()) {
+        debug!("Reusing the pool of an earlier session");
+        return Ok(pool.clone());
+    }
+    let pool = connect_with_retry(database_url, max_connections).await?;
+    pools.insert(database_url.to_string(), pool.clone());
+    Ok(pool)
+}
+
+async fn connect_with_retry(database_url: &str, max_connections: u32) -> Result<Pool<MySql>, Box<dyn std::error::Error>> {
     let mut retry_count = 0;
     const MAX_RETRIES: u32 = 5;
     const RETRY_DELAY_MS: u64 = 1000;
     
     loop {
         match sqlx::mysql::MySqlPoolOptions::new()
-            .max_connections(5)
+            .max_connections(max_connections)
             .connect(database_url)
             .await
         {
@@ -305,11 +910,62 @@ fn create_error_response(id: Option<Value>, code: i32, message: &str) -> JsonRpc
     }
 }
 
+// The `database` argument shared by the schema and query tools
+fn database_property() -> Value {
+    json!({
+        "type": "string",
+        "description": "Database to use instead of the configured one, see list_databases"
+    })
+}
+
+// The database tools use by default: the one in the connection URL, which may come from
+// initializationOptions rather than --database
+fn default_database(args: &Args, pool: &Pool<MySql>) -> String {
+    pool.connect_options().get_database().unwrap_or(&args.database).to_string()
+}
+
+// The database a tool call should use: the default one, or a requested one from --allowed-databases
+fn resolve_database(args: &Args, pool: &Pool<MySql>, requested: Option<&str>) -> Result<String, String> {
+    let current = default_database(args, pool);
+    match requested {
+        None => Ok(current),
+        Some(database) if database == current || database_allowed(args, database) => Ok(database.to_string()),
+        Some(database) => Err(format!(
+            "Database '{database}' is not allowed. Add it to --allowed-databases to use it."
+        )),
+    }
+}
+
+fn database_allowed(args: &Args, database: &str) -> bool {
+    args.allowed_databases
+        .iter()
+        .any(|allowed| allowed == "*" || allowed == database)
+}
+
+// With a restrictive --allowed-databases, a query may only name databases on the list besides the
+// one it runs in and the default one. information_schema holds metadata only and stays readable.
+fn check_query_databases(args: &Args, default: &str, current: &str, query: &str) -> Result<(), String> {
+    if args.allowed_databases.is_empty() || database_allowed(args, "*") {
+        return Ok(());
+    }
+    let denied = sql_guard::referenced_databases(query)?.into_iter().find(|database| {
+        database != current
+            && database != default
+            && !database.eq_ignore_ascii_case("information_schema")
+            && !database_allowed(args, database)
+    });
+    match denied {
+        Some(database) => Err(format!("Database '{database}' is not allowed. Add it to --allowed-databases to use it.")),
+        None => Ok(()),
+    }
+}
+
 async fn handle_request(
     request: JsonRpcRequest,
     pool: &mut Option<Pool<MySql>>,
     args: &Args,
     allow_dangerous_queries: bool,
+    ctx: &RequestContext,
 ) -> JsonRpcResponse {
     match request.method.as_str() {
         "initialize" => {
@@ -330,10 +986,7 @@ async fn handle_request(
                     url
                 }
                 None => {
-                    let url = format!(
-                        "mysql://{}:{}@{}:{}/{}",
-                        args.username, args.password, args.host, args.port, args.database
-                    );
+                    let url = database_url(args);
                     info!("Using database_url from command-line arguments: mysql://{}:***@{}:{}/{}", 
                              args.username, args.host, args.port, args.database);
                     url
@@ -341,7 +994,7 @@ async fn handle_request(
             };
 
             info!("Attempting database connection...");
-            match connect_with_retry(&database_url).await {
+            match connect(&database_url, args.max_connections, ctx.shared_pools.as_ref()).await {
                 Ok(new_pool) => {
                     info!("Database connection successful!");
                     *pool = Some(new_pool);
@@ -352,8 +1005,15 @@ async fn handle_request(
                             protocol_version: "2025-03-26".to_string(),
                             capabilities: ServerCapabilities {
                                 tools: Some(ToolsCapability {
+                                    list_changed: false,
+                                }),
+                                resources: Some(ResourcesCapability {
+                                    subscribe: false,
                                     list_changed: true,
                                 }),
+                                prompts: Some(PromptsCapability {
+                                    list_changed: false,
+                                }),
                             },
                             server_info: ServerInfo {
                                 name: "mcp-server-mysql".to_string(),
@@ -384,6 +1044,12 @@ async fn handle_request(
                 error: None,
             }
         }
+        "ping" => JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: request.id,
+            result: Some(json!({})),
+            error: None,
+        },
         "tools/list" => {
     debug!("Listing available tools");
     JsonRpcResponse {
@@ -391,9 +1057,18 @@ async fn handle_request(
         id: request.id,
         result: Some(json!(ToolsList {
             tools: vec![
+                Tool {
+                    name: "list_databases".to_string(),
+                    description: "List the databases the schema and query tools can use through their 'database' argument"
+                        .to_string(),
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": {}
+                    }),
+                },
                 Tool {
                     name: "mysql".to_string(),
-                    description: "Retrieve MySQL database schema information for tables"
+                    description: "Retrieve MySQL database schema information for tables, including foreign keys"
                         .to_string(),
                     input_schema: json!({
                         "type": "object",
@@ -401,31 +1076,191 @@ async fn handle_request(
                             "table_name": {
                                 "type": "string",
                                 "description": "Name of the table to inspect, or 'all-tables' to get all table schemas"
-                            }
+                            },
+                            "detail": {
+                                "type": "string",
+                                "enum": ["names", "compact", "full"],
+                                "description": "Level of detail: table names only (with 'all-tables' only), one compact line per table such as 'orders(id PK int, user_id FK→users.id int, …)', or the full JSON schema (default)"
+                            },
+                            "database": database_property()
                         },
                         "required": ["table_name"]
                     }),
                 },
                 Tool {
-                    name: "query".to_string(),
-                    description: if allow_dangerous_queries {
-                        "Execute any SQL query on the database (unrestricted)".to_string()
-                    } else {
-                        "Execute a SELECT query on the database (read-only)".to_string()
-                    },
+                    name: "schema_objects".to_string(),
+                    description: "List or describe views, stored procedures and functions, triggers and scheduled events"
+                        .to_string(),
                     input_schema: json!({
                         "type": "object",
                         "properties": {
-                            "query": {
+                            "kind": {
                                 "type": "string",
-                                "description": if allow_dangerous_queries {
+                                "enum": ["views", "routines", "triggers", "events"],
+                                "description": "Kind of object to list"
+                            },
+                            "name": {
+                                "type": "string",
+                                "description": "Name of one object to describe, including its definition or body"
+                            },
+                            "database": database_property()
+                        },
+                        "required": ["kind"]
+                    }),
+                },
+                Tool {
+                    name: "search_schema".to_string(),
+                    description: "Search table and column names and comments by keywords, returning the best matching tables and columns with their types"
+                        .to_string(),
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": {
+                            "query": {
+                                "type": "string",
+                                "description": "Keywords such as 'invoice total'; '*' or '%' inside a keyword matches any characters"
+                            },
+                            "limit": {
+                                "type": "integer",
+                                "description": "Maximum number of matches to return (default 50)"
+                            },
+                            "database": database_property()
+                        },
+                        "required": ["query"]
+                    }),
+                },
+                Tool {
+                    name: "ddl".to_string(),
+                    description: "Get the exact CREATE statement of a table, view, procedure, function or trigger, or of the whole database in dependency order"
+                        .to_string(),
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": {
+                            "kind": {
+                                "type": "string",
+                                "enum": ddl::KINDS,
+                                "description": "Kind of the object named by 'name'"
+                            },
+                            "name": {
+                                "type": "string",
+                                "description": "Name of the object; omit to export the whole database"
+                            },
+                            "database": database_property()
+                        }
+                    }),
+                },
+                Tool {
+                    name: "er_diagram".to_string(),
+                    description: "Draw an entity-relationship diagram of the database, or of some tables and their foreign key neighbours, as Mermaid or Graphviz DOT"
+                        .to_string(),
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": {
+                            "format": {
+                                "type": "string",
+                                "enum": diagram::FORMATS,
+                                "description": "Diagram format (default mermaid)"
+                            },
+                            "tables": {
+                                "type": "array",
+                                "items": { "type": "string" },
+                                "description": "Tables to start from; omit to draw the whole database"
+                            },
+                            "hops": {
+                                "type": "integer",
+                                "description": "Number of foreign key hops to follow from the given tables (default 1)"
+                            },
+                            "database": database_property()
+                        }
+                    }),
+                },
+                Tool {
+                    name: "schema_snapshot".to_string(),
+                    description: "Take a versioned JSON snapshot of every table schema, to save and compare later with schema_diff"
+                        .to_string(),
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": {
+                            "database": database_property()
+                        }
+                    }),
+                },
+                Tool {
+                    name: "schema_diff".to_string(),
+                    description: "Compare two schema snapshots, or a snapshot with the live database, listing added, removed and changed tables, columns, indexes and foreign keys"
+                        .to_string(),
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": {
+                            "from": {
+                                "type": ["object", "string"],
+                                "description": "Older snapshot, inline or as the name of a file in the server's snapshot directory"
+                            },
+                            "to": {
+                                "type": ["object", "string"],
+                                "description": "Newer snapshot, inline or as a file name; omit to compare with the live database"
+                            },
+                            "database": database_property()
+                        },
+                        "required": ["from"]
+                    }),
+                },
+                Tool {
+                    name: "profile_table".to_string(),
+                    description: "Profile the values in a table: a few sample rows, and per column the null fraction, distinct count, min/max and most frequent values"
+                        .to_string(),
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": {
+                            "table_name": {
+                                "type": "string",
+                                "description": "Name of the table to profile"
+                            },
+                            "sample_rows": {
+                                "type": "integer",
+                                "description": "Number of random sample rows to return (default 5)"
+                            },
+                            "top_values": {
+                                "type": "integer",
+                                "description": "Number of most frequent values to list for columns with few distinct values (default 5)"
+                            },
+                            "scan_rows": {
+                                "type": "integer",
+                                "description": format!("Maximum number of rows to read (default and maximum {})", args.profile_scan_rows)
+                            },
+                            "database": database_property()
+                        },
+                        "required": ["table_name"]
+                    }),
+                },
+                Tool {
+                    name: "query".to_string(),
+                    description: if allow_dangerous_queries {
+                        "Execute any SQL query on the database (unrestricted)".to_string()
+                    } else {
+                        "Execute a read-only query (SELECT, SHOW, DESCRIBE, EXPLAIN) on the database".to_string()
+                    },
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": {
+                            "query": {
+                                "type": "string",
+                                "description": if allow_dangerous_queries {
                                     "SQL query to execute"
                                 } else {
-                                    "SELECT query to execute"
+                                    "Read-only query to execute (SELECT, WITH, SHOW, DESCRIBE or EXPLAIN)"
                                 }
-                            }
-                        },
-                        "required": ["query"]
+                            },
+                            "cursor": {
+                                "type": "string",
+                                "description": "Cursor from a truncated result, fetches the next page of that query (query is then ignored)"
+                            },
+                            "timeout_ms": {
+                                "type": "integer",
+                                "minimum": 1,
+                                "description": "Timeout for this query in milliseconds; it can shorten the server's timeout but not extend it"
+                            },
+                            "database": database_property()
+                        }
                     }),
                 },
                         Tool {
@@ -503,11 +1338,15 @@ async fn handle_request(
                 Some(params) => match serde_json::from_value::<ToolCallParams>(params) {
                     Ok(tool_params) => {
                         match tool_params.name.as_str() {
+                            "list_databases" => list_databases(request.id, args, current_pool).await,
                             "mysql" => {
                                 match serde_json::from_value::<SchemaArguments>(tool_params.arguments) {
-                                    Ok(schema_args) => {
-                                        get_schema(request.id, schema_args.table_name, current_pool).await
-                                    }
+                                    Ok(schema_args) => match resolve_database(args, current_pool, schema_args.database.as_deref()) {
+                                        Ok(database) => {
+                                            get_schema(request.id, &database, schema_args.table_name, &schema_args.detail, current_pool, ctx).await
+                                        }
+                                        Err(message) => create_error_response(request.id, -32602, &message),
+                                    },
                                     Err(e) => JsonRpcResponse {
                                         jsonrpc: "2.0".to_string(),
                                         id: request.id,
@@ -520,10 +1359,184 @@ async fn handle_request(
                                     },
                                 }
                             }
+                            "schema_objects" => {
+                                match serde_json::from_value::<SchemaObjectsArguments>(tool_params.arguments) {
+                                    Ok(objects_args) => match resolve_database(args, current_pool, objects_args.database.as_deref()) {
+                                        Ok(database) => {
+                                            get_schema_objects(request.id, &database, &objects_args.kind, objects_args.name.as_deref(), current_pool).await
+                                        }
+                                        Err(message) => create_error_response(request.id, -32602, &message),
+                                    },
+                                    Err(e) => JsonRpcResponse {
+                                        jsonrpc: "2.0".to_string(),
+                                        id: request.id,
+                                        result: None,
+                                        error: Some(JsonRpcError {
+                                            code: -32602,
+                                            message: format!("Invalid schema_objects arguments: {e}"),
+                                            data: None,
+                                        }),
+                                    },
+                                }
+                            }
+                            "search_schema" => {
+                                match serde_json::from_value::<SearchArguments>(tool_params.arguments) {
+                                    Ok(search_args) => match resolve_database(args, current_pool, search_args.database.as_deref()) {
+                                        Ok(database) => {
+                                            search_schema(request.id, &database, &search_args.query, search_args.limit.unwrap_or(50), current_pool).await
+                                        }
+                                        Err(message) => create_error_response(request.id, -32602, &message),
+                                    },
+                                    Err(e) => JsonRpcResponse {
+                                        jsonrpc: "2.0".to_string(),
+                                        id: request.id,
+                                        result: None,
+                                        error: Some(JsonRpcError {
+                                            code: -32602,
+                                            message: format!("Invalid search arguments: {e}"),
+                                            data: None,
+                                        }),
+                                    },
+                                }
+                            }
+                            "er_diagram" => {
+                                match serde_json::from_value::<DiagramArguments>(tool_params.arguments) {
+                                    Ok(diagram_args) => match resolve_database(args, current_pool, diagram_args.database.as_deref()) {
+                                        Ok(database) => get_er_diagram(request.id, &database, &diagram_args, current_pool, ctx).await,
+                                        Err(message) => create_error_response(request.id, -32602, &message),
+                                    },
+                                    Err(e) => JsonRpcResponse {
+                                        jsonrpc: "2.0".to_string(),
+                                        id: request.id,
+                                        result: None,
+                                        error: Some(JsonRpcError {
+                                            code: -32602,
+                                            message: format!("Invalid er_diagram arguments: {e}"),
+                                            data: None,
+                                        }),
+                                    },
+                                }
+                            }
+                            "schema_snapshot" => {
+                                match serde_json::from_value::<SnapshotArguments>(tool_params.arguments) {
+                                    Ok(snapshot_args) => match resolve_database(args, current_pool, snapshot_args.database.as_deref()) {
+                                        Ok(database) => get_schema_snapshot(request.id, &database, current_pool, ctx).await,
+                                        Err(message) => create_error_response(request.id, -32602, &message),
+                                    },
+                                    Err(e) => JsonRpcResponse {
+                                        jsonrpc: "2.0".to_string(),
+                                        id: request.id,
+                                        result: None,
+                                        error: Some(JsonRpcError {
+                                            code: -32602,
+                                            message: format!("Invalid schema_snapshot arguments: {e}"),
+                                            data: None,
+                                        }),
+                                    },
+                                }
+                            }
+                            "schema_diff" => {
+                                match serde_json::from_value::<DiffArguments>(tool_params.arguments) {
+                                    Ok(diff_args) => match resolve_database(args, current_pool, diff_args.database.as_deref()) {
+                                        Ok(database) => get_schema_diff(request.id, &database, &diff_args, args.snapshot_dir.as_deref(), current_pool, ctx).await,
+                                        Err(message) => create_error_response(request.id, -32602, &message),
+                                    },
+                                    Err(e) => JsonRpcResponse {
+                                        jsonrpc: "2.0".to_string(),
+                                        id: request.id,
+                                        result: None,
+                                        error: Some(JsonRpcError {
+                                            code: -32602,
+                                            message: format!("Invalid schema_diff arguments: {e}"),
+                                            data: None,
+                                        }),
+                                    },
+                                }
+                            }
+                            "profile_table" => {
+                                match serde_json::from_value::<ProfileArguments>(tool_params.arguments) {
+                                    Ok(profile_args) => match resolve_database(args, current_pool, profile_args.database.as_deref()) {
+                                        Ok(database) => get_table_profile(request.id, &database, &profile_args, args, current_pool, ctx).await,
+                                        Err(message) => create_error_response(request.id, -32602, &message),
+                                    },
+                                    Err(e) => JsonRpcResponse {
+                                        jsonrpc: "2.0".to_string(),
+                                        id: request.id,
+                                        result: None,
+                                        error: Some(JsonRpcError {
+                                            code: -32602,
+                                            message: format!("Invalid profile_table arguments: {e}"),
+                                            data: None,
+                                        }),
+                                    },
+                                }
+                            }
+                            "ddl" => {
+                                match serde_json::from_value::<DdlArguments>(tool_params.arguments) {
+                                    Ok(ddl_args) => match resolve_database(args, current_pool, ddl_args.database.as_deref()) {
+                                        Ok(database) => {
+                                            get_ddl(request.id, &database, ddl_args.kind.as_deref(), ddl_args.name.as_deref(), current_pool, ctx).await
+                                        }
+                                        Err(message) => create_error_response(request.id, -32602, &message),
+                                    },
+                                    Err(e) => JsonRpcResponse {
+                                        jsonrpc: "2.0".to_string(),
+                                        id: request.id,
+                                        result: None,
+                                        error: Some(JsonRpcError {
+                                            code: -32602,
+                                            message: format!("Invalid ddl arguments: {e}"),
+                                            data: None,
+                                        }),
+                                    },
+                                }
+                            }
                             "query" => {
                                 match serde_json::from_value::<QueryArguments>(tool_params.arguments) {
                                     Ok(query_args) => {
-                                        execute_query(request.id.clone().unwrap_or(json!(null)), query_args.query, current_pool, allow_dangerous_queries).await
+                                        let id = request.id.clone().unwrap_or(json!(null));
+                                        let cursor = match (query_args.cursor, query_args.query) {
+                                            (Some(cursor), _) => match decode_cursor(&cursor) {
+                                                Some(cursor) => cursor,
+                                                None => return create_error_response(Some(id), -32602, "Invalid cursor"),
+                                            },
+                                            (None, Some(query)) => QueryCursor {
+                                                query,
+                                                offset: 0,
+                                                database: query_args.database,
+                                            },
+                                            (None, None) => {
+                                                return create_error_response(Some(id), -32602, "Either query or cursor is required");
+                                            }
+                                        };
+                                        // Cursors come from the client, so check their database too
+                                        let database = match resolve_database(args, current_pool, cursor.database.as_deref()) {
+                                            Ok(database) => database,
+                                            Err(message) => return create_error_response(Some(id), -32602, &message),
+                                        };
+                                        let default = default_database(args, current_pool);
+                                        if let Err(message) = check_query_databases(args, &default, &database, &cursor.query) {
+                                            return create_error_response(Some(id), -32602, &message);
+                                        }
+                                        let cursor = QueryCursor {
+                                            database: Some(database).filter(|database| *database != default),
+                                            ..cursor
+                                        };
+                                        if query_args.timeout_ms == Some(0) {
+                                            return create_error_response(Some(id), -32602, "timeout_ms must be at least 1");
+                                        }
+                                        let timeout = match (query_args.timeout_ms, args.query_timeout) {
+                                            (Some(ms), 0) => Duration::from_millis(ms),
+                                            // A call may shorten the server's timeout, but not lift or extend it
+                                            (Some(ms), seconds) => Duration::from_millis(ms.min(seconds.saturating_mul(1000))),
+                                            (None, seconds) => Duration::from_secs(seconds),
+                                        };
+                                        let limits = QueryLimits {
+                                            max_rows: args.max_rows,
+                                            max_bytes: args.max_bytes,
+                                            timeout: Some(timeout).filter(|timeout| !timeout.is_zero()),
+                                        };
+                                        execute_query(id, cursor, current_pool, allow_dangerous_queries, limits, ctx).await
                                     }
                                     Err(e) => JsonRpcResponse {
                                         jsonrpc: "2.0".to_string(),
@@ -623,6 +1636,109 @@ async fn handle_request(
                 },
             }
         }
+        "resources/list" => {
+            let current_pool = match pool.as_ref() {
+                Some(p) => p,
+                None => {
+                    return create_error_response(request.id, -32002, "Server not initialized");
+                }
+            };
+            debug!("Listing table resources");
+            let current_db = &default_database(args, current_pool);
+            match cached_table_names(current_pool, current_db, ctx).await {
+                Ok(tables) => JsonRpcResponse {
+                    jsonrpc: "2.0".to_string(),
+                    id: request.id,
+                    result: Some(json!(ResourcesList {
+                        resources: tables
+                            .into_iter()
+                            .map(|table_name| Resource {
+                                uri: table_schema_uri(current_db, &table_name),
+                                description: format!("Schema of table '{table_name}' in database '{current_db}'"),
+                                name: table_name,
+                                mime_type: "application/json".to_string(),
+                            })
+                            .collect(),
+                    })),
+                    error: None,
+                },
+                Err(e) => {
+                    error!("Database error listing table resources: {e}");
+                    create_error_response(request.id, -32603, &format!("Failed to list tables: {e}"))
+                }
+            }
+        }
+        "resources/templates/list" => {
+            debug!("Listing resource templates");
+            JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id: request.id,
+                result: Some(json!(ResourceTemplatesList {
+                    resource_templates: vec![ResourceTemplate {
+                        uri_template: "mysql://{database}/{table}/schema".to_string(),
+                        name: "Table schema".to_string(),
+                        description: "Columns, indexes and foreign keys of a table in the current or an allowed database".to_string(),
+                        mime_type: "application/json".to_string(),
+                    }],
+                })),
+                error: None,
+            }
+        }
+        "resources/read" => {
+            let current_pool = match pool.as_ref() {
+                Some(p) => p,
+                None => {
+                    return create_error_response(request.id, -32002, "Server not initialized");
+                }
+            };
+            let read_params = match request
+                .params
+                .map(serde_json::from_value::<ResourceReadParams>)
+            {
+                Some(Ok(read_params)) => read_params,
+                Some(Err(e)) => {
+                    return create_error_response(request.id, -32602, &format!("Invalid resource read parameters: {e}"));
+                }
+                None => {
+                    return create_error_response(request.id, -32602, "Missing parameters");
+                }
+            };
+            debug!("Reading resource: {}", read_params.uri);
+            read_resource(request.id, &read_params.uri, args, current_pool, ctx).await
+        }
+        "prompts/list" => {
+            debug!("Listing available prompts");
+            JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id: request.id,
+                result: Some(json!(PromptsList {
+                    prompts: prompt_definitions(),
+                })),
+                error: None,
+            }
+        }
+        "prompts/get" => {
+            let current_pool = match pool.as_ref() {
+                Some(p) => p,
+                None => {
+                    return create_error_response(request.id, -32002, "Server not initialized");
+                }
+            };
+            let prompt_params = match request
+                .params
+                .map(serde_json::from_value::<PromptGetParams>)
+            {
+                Some(Ok(prompt_params)) => prompt_params,
+                Some(Err(e)) => {
+                    return create_error_response(request.id, -32602, &format!("Invalid prompt parameters: {e}"));
+                }
+                None => {
+                    return create_error_response(request.id, -32602, "Missing parameters");
+                }
+            };
+            debug!("Getting prompt: {}", prompt_params.name);
+            get_prompt(request.id, prompt_params, &default_database(args, current_pool), current_pool, ctx).await
+        }
         _ => {
             warn!("Unknown method: {}", request.method);
             JsonRpcResponse {
@@ -639,28 +1755,146 @@ async fn handle_request(
     }
 }
 
+async fn list_databases(id: Option<Value>, args: &Args, pool: &Pool<MySql>) -> JsonRpcResponse {
+    debug!("Listing databases");
+
+    let rows = match sqlx::query(
+        "SELECT schema_name AS schema_name, default_character_set_name AS default_character_set_name,
+                default_collation_name AS default_collation_name
+         FROM information_schema.schemata
+         ORDER BY schema_name",
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(rows) => rows,
+        Err(e) => {
+            error!("Database error listing databases: {e}");
+            return create_error_response(id, -32603, &format!("Failed to list databases: {e}"));
+        }
+    };
+
+    let current_database = default_database(args, pool);
+    let databases: Vec<Value> = rows
+        .into_iter()
+        .filter_map(|row| {
+            let name = row.try_get::<String, _>("schema_name").unwrap_or_default();
+            let current = name == current_database;
+            (current || database_allowed(args, &name)).then(|| {
+                json!({
+                    "name": name,
+                    "current": current,
+                    "character_set": row.try_get::<String, _>("default_character_set_name").unwrap_or_default(),
+                    "collation": row.try_get::<String, _>("default_collation_name").unwrap_or_default(),
+                })
+            })
+        })
+        .collect();
+
+    let lines: Vec<String> = databases
+        .iter()
+        .map(|database| {
+            let name = database["name"].as_str().unwrap_or_default();
+            if database["current"] == true {
+                format!("{name} (current)")
+            } else {
+                name.to_string()
+            }
+        })
+        .collect();
+    JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id,
+        result: Some(json!({
+            "content": [{
+                "type": "text",
+                "text": format!("{} databases available:\n{}", databases.len(), lines.join("\n"))
+            }],
+            "databases": databases
+        })),
+        error: None,
+    }
+}
+
 async fn get_schema(
     id: Option<Value>,
+    database: &str,
     table_name: String,
+    detail: &str,
     pool: &Pool<MySql>,
+    ctx: &RequestContext,
 ) -> JsonRpcResponse {
-    debug!("Getting schema for: {table_name}");
+    debug!("Getting schema for: {table_name} ({detail})");
+
+    if !matches!(detail, "names" | "compact" | "full") {
+        return create_error_response(
+            id,
+            -32602,
+            &format!("Unknown detail '{detail}', expected names, compact or full"),
+        );
+    }
+
+    if table_name != "all-tables" && detail == "names" {
+        return create_error_response(
+            id,
+            -32602,
+            "detail 'names' lists the tables of a database and needs table_name 'all-tables'",
+        );
+    }
+
+    if table_name == "all-tables" && detail == "names" {
+        // Names only need a single query
+        return match cached_table_names(pool, database, ctx).await {
+            Ok(tables) => JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id,
+                result: Some(json!({
+                    "content": [{
+                        "type": "text",
+                        "text": format!("{} tables in '{database}':\n{}", tables.len(), tables.join("\n"))
+                    }],
+                    "tables": tables
+                })),
+                error: None,
+            },
+            Err(e) => {
+                error!("Database error getting table names: {e}");
+                create_error_response(id, -32603, &format!("Failed to get table names: {e}"))
+            }
+        };
+    }
     
     if table_name == "all-tables" {
         // Get all table schemas
-        match get_all_table_schemas(pool).await {
+        match get_all_table_schemas(pool, database, ctx).await {
             Ok(schemas) => {
                 info!("Successfully retrieved schemas for {} tables", schemas.len());
-                JsonRpcResponse {
-                    jsonrpc: "2.0".to_string(),
-                    id,
-                    result: Some(json!({
+                // Most clients only show `content`, so it carries the schemas themselves
+                let result = if detail == "compact" {
+                    let lines: Vec<String> = schemas.iter().map(compact_schema).collect();
+                    json!({
+                        "content": [{
+                            "type": "text",
+                            "text": lines.join("\n")
+                        }]
+                    })
+                } else {
+                    json!({
                         "content": [{
                             "type": "text",
-                            "text": format!("Retrieved schemas for {} tables.", schemas.len())
+                            "text": format!(
+                                "Retrieved schemas for {} tables.\n\n{}",
+                                schemas.len(),
+                                serde_json::to_string_pretty(&schemas).unwrap_or_default()
+                            )
                         }],
                         "schemas": schemas
-                    })),
+                    })
+                };
+                JsonRpcResponse {
+                    jsonrpc: "2.0".to_string(),
+                    id,
+                    result: Some(result),
                     error: None,
                 }
             }
@@ -671,7 +1905,7 @@ async fn get_schema(
                     id,
                     result: None,
                     error: Some(JsonRpcError {
-                        code: -32603,
+                        code: e.code(),
                         message: format!("Failed to get table schemas: {e}"),
                         data: None,
                     }),
@@ -680,35 +1914,693 @@ async fn get_schema(
         }
     } else {
         // Get single table schema
-        match get_table_schema(pool, &table_name).await {
+        match cached_table_schema(pool, database, &table_name, ctx).await {
             Ok(schema) => {
                 info!("Successfully retrieved schema for table '{table_name}'");
+                let result = if detail == "full" {
+                    json!({
+                        "content": [{
+                            "type": "text",
+                            "text": format!(
+                                "Retrieved schema for table '{}'.\n\n{}",
+                                table_name,
+                                serde_json::to_string_pretty(&schema).unwrap_or_default()
+                            )
+                        }],
+                        "schema": schema
+                    })
+                } else {
+                    json!({
+                        "content": [{
+                            "type": "text",
+                            "text": compact_schema(&schema)
+                        }]
+                    })
+                };
+                JsonRpcResponse {
+                    jsonrpc: "2.0".to_string(),
+                    id,
+                    result: Some(result),
+                    error: None,
+                }
+            }
+            Err(e) => {
+                error!("Database error getting schema for table '{table_name}': {e}");
+                JsonRpcResponse {
+                    jsonrpc: "2.0".to_string(),
+                    id,
+                    result: None,
+                    error: Some(JsonRpcError {
+                        code: e.code(),
+                        message: format!("Failed to get schema for table '{table_name}': {e}"),
+                        data: None,
+                    }),
+                }
+            }
+        }
+    }
+}
+
+// One line per table, e.g. `orders(id PK int, user_id FK→users.id int, note text NULL)`,
+// followed by the table comment if there is one
+fn compact_schema(schema: &Value) -> String {
+    let empty = Vec::new();
+    let foreign_keys = schema["foreign_keys"].as_array().unwrap_or(&empty);
+
+    let columns: Vec<String> = schema["columns"]
+        .as_array()
+        .unwrap_or(&empty)
+        .iter()
+        .map(|column| {
+            let name = column["name"].as_str().unwrap_or_default();
+            let mut parts = vec![name.to_string()];
+            match column["key"].as_str() {
+                Some("PRI") => parts.push("PK".to_string()),
+                Some("UNI") => parts.push("UQ".to_string()),
+                _ => {}
+            }
+            for foreign_key in foreign_keys {
+                let columns = foreign_key["columns"].as_array().unwrap_or(&empty);
+                if let Some(position) = columns.iter().position(|c| c == name) {
+                    let mut target = foreign_key["referenced_table"].as_str().unwrap_or_default().to_string();
+                    if foreign_key["referenced_schema"] != foreign_key["schema"] {
+                        target = format!("{}.{target}", foreign_key["referenced_schema"].as_str().unwrap_or_default());
+                    }
+                    let referenced = &foreign_key["referenced_columns"][position];
+                    parts.push(format!("FK→{target}.{}", referenced.as_str().unwrap_or_default()));
+                }
+            }
+            parts.push(column["column_type"].as_str().unwrap_or_default().to_string());
+            if column["nullable"] == true {
+                parts.push("NULL".to_string());
+            }
+            parts.join(" ")
+        })
+        .collect();
+
+    let mut line = format!("{}({})", schema["table_name"].as_str().unwrap_or_default(), columns.join(", "));
+    if let Some(comment) = schema["comment"].as_str().filter(|c| !c.is_empty()) {
+        line.push_str(&format!(" -- {comment}"));
+    }
+    line
+}
+
+fn prompt_definitions() -> Vec<Prompt> {
+    vec![
+        Prompt {
+            name: "explain-table".to_string(),
+            description: "Explain what a table stores, its columns and how it is indexed".to_string(),
+            arguments: vec![PromptArgument {
+                name: "table_name".to_string(),
+                description: "Name of the table to explain".to_string(),
+                required: true,
+            }],
+        },
+        Prompt {
+            name: "write-query".to_string(),
+            description: "Write a SQL query for a task using the live database schema".to_string(),
+            arguments: vec![
+                PromptArgument {
+                    name: "task".to_string(),
+                    description: "What the query should do".to_string(),
+                    required: true,
+                },
+                PromptArgument {
+                    name: "table_name".to_string(),
+                    description: "Restrict the schema to this table (defaults to all tables)".to_string(),
+                    required: false,
+                },
+            ],
+        },
+        Prompt {
+            name: "review-migration".to_string(),
+            description: "Review a schema migration against the current database schema".to_string(),
+            arguments: vec![PromptArgument {
+                name: "migration".to_string(),
+                description: "SQL of the migration to review".to_string(),
+                required: true,
+            }],
+        },
+    ]
+}
+
+async fn get_prompt(
+    id: Option<Value>,
+    params: PromptGetParams,
+    database: &str,
+    pool: &Pool<MySql>,
+    ctx: &RequestContext,
+) -> JsonRpcResponse {
+    let argument = |name: &str| params.arguments.get(name).map(|v| v.trim()).filter(|v| !v.is_empty());
+
+    let (description, text) = match params.name.as_str() {
+        "explain-table" => {
+            let table_name = match argument("table_name") {
+                Some(t) => t,
+                None => return create_error_response(id, -32602, "Missing required argument: table_name"),
+            };
+            let schema = match cached_table_schema(pool, database, table_name, ctx).await {
+                Ok(schema) => schema,
+                Err(e) => {
+                    error!("Database error building prompt for table '{table_name}': {e}");
+                    return create_error_response(id, e.code(), &format!("Failed to get schema for table '{table_name}': {e}"));
+                }
+            };
+            (
+                format!("Explain the '{table_name}' table"),
+                format!(
+                    "Explain what the MySQL table `{table_name}` stores, what each column means, \
+                     how it is indexed and how it is likely used.\n\nSchema:\n{}",
+                    serde_json::to_string_pretty(&schema).unwrap_or_default()
+                ),
+            )
+        }
+        "write-query" => {
+            let task = match argument("task") {
+                Some(t) => t,
+                None => return create_error_response(id, -32602, "Missing required argument: task"),
+            };
+            let schema = match argument("table_name") {
+                Some(table_name) => cached_table_schema(pool, database, table_name, ctx).await,
+                None => get_all_table_schemas(pool, database, ctx).await.map(Value::from),
+            };
+            let schema = match schema {
+                Ok(schema) => schema,
+                Err(e) => {
+                    error!("Database error building query prompt: {e}");
+                    return create_error_response(id, e.code(), &format!("Failed to get table schemas: {e}"));
+                }
+            };
+            (
+                "Write a SQL query".to_string(),
+                format!(
+                    "Write a MySQL query for the following task:\n{task}\n\n\
+                     Only use tables and columns from this schema:\n{}",
+                    serde_json::to_string_pretty(&schema).unwrap_or_default()
+                ),
+            )
+        }
+        "review-migration" => {
+            let migration = match argument("migration") {
+                Some(m) => m,
+                None => return create_error_response(id, -32602, "Missing required argument: migration"),
+            };
+            let schemas = match get_all_table_schemas(pool, database, ctx).await {
+                Ok(schemas) => schemas,
+                Err(e) => {
+                    error!("Database error building migration prompt: {e}");
+                    return create_error_response(id, e.code(), &format!("Failed to get table schemas: {e}"));
+                }
+            };
+            (
+                "Review a migration".to_string(),
+                format!(
+                    "Review this MySQL migration against the current schema. Point out errors, \
+                     data loss, locking or performance risks, and missing indexes.\n\n\
+                     Migration:\n```sql\n{migration}\n```\n\nCurrent schema:\n{}",
+                    serde_json::to_string_pretty(&schemas).unwrap_or_default()
+                ),
+            )
+        }
+        _ => return create_error_response(id, -32602, &format!("Unknown prompt: {}", params.name)),
+    };
+
+    JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id,
+        result: Some(json!({
+            "description": description,
+            "messages": [{
+                "role": "user",
+                "content": {
+                    "type": "text",
+                    "text": text
+                }
+            }]
+        })),
+        error: None,
+    }
+}
+
+fn table_schema_uri(database: &str, table_name: &str) -> String {
+    format!("mysql://{}/{}/schema", percent_encode(database), percent_encode(table_name))
+}
+
+// Split a `mysql://<db>/<table>/schema` URI into its decoded database and table parts
+fn parse_table_schema_uri(uri: &str) -> Option<(String, String)> {
+    let path = uri.strip_prefix("mysql://")?;
+    let path = path.strip_suffix("/schema")?;
+    let (database, table_name) = path.split_once('/')?;
+    let (database, table_name) = (percent_decode(database)?, percent_decode(table_name)?);
+    if database.is_empty() || table_name.is_empty() {
+        return None;
+    }
+    Some((database, table_name))
+}
+
+// Identifiers may contain `/`, `?`, spaces or any other character, so everything but the
+// unreserved characters of RFC 3986 is escaped as UTF-8 bytes
+fn percent_encode(name: &str) -> String {
+    let mut encoded = String::with_capacity(name.len());
+    for byte in name.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+fn percent_decode(encoded: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut rest = encoded.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+async fn read_resource(
+    id: Option<Value>,
+    uri: &str,
+    args: &Args,
+    pool: &Pool<MySql>,
+    ctx: &RequestContext,
+) -> JsonRpcResponse {
+    let (database, table_name) = match parse_table_schema_uri(uri) {
+        Some(parts) => parts,
+        None => {
+            return create_error_response(id, -32602, &format!("Unknown resource URI: {uri}"));
+        }
+    };
+
+    if let Err(message) = resolve_database(args, pool, Some(&database)) {
+        return create_error_response(id, -32602, &message);
+    }
+
+    match cached_table_schema(pool, &database, &table_name, ctx).await {
+        Ok(schema) => JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: Some(json!({
+                "contents": [{
+                    "uri": uri,
+                    "mimeType": "application/json",
+                    "text": schema.to_string()
+                }]
+            })),
+            error: None,
+        },
+        Err(e) => {
+            error!("Database error reading resource '{uri}': {e}");
+            create_error_response(id, e.code(), &format!("Failed to read resource '{uri}': {e}"))
+        }
+    }
+}
+
+async fn get_schema_objects(
+    id: Option<Value>,
+    database: &str,
+    kind: &str,
+    name: Option<&str>,
+    pool: &Pool<MySql>,
+) -> JsonRpcResponse {
+    debug!("Getting schema objects: kind={kind}, name={name:?}");
+
+    let objects = match kind {
+        "views" => get_views(pool, database, name).await,
+        "routines" => get_routines(pool, database, name).await,
+        "triggers" => get_triggers(pool, database, name).await,
+        "events" => get_events(pool, database, name).await,
+        _ => {
+            return create_error_response(
+                id,
+                -32602,
+                &format!("Unknown object kind '{kind}', expected views, routines, triggers or events"),
+            );
+        }
+    };
+
+    match objects {
+        Ok(objects) => match name {
+            Some(name) => match objects.into_iter().next() {
+                Some(object) => JsonRpcResponse {
+                    jsonrpc: "2.0".to_string(),
+                    id,
+                    result: Some(json!({
+                        "content": [{
+                            "type": "text",
+                            "text": serde_json::to_string_pretty(&object).unwrap_or_default()
+                        }],
+                        "object": object
+                    })),
+                    error: None,
+                },
+                None => create_error_response(id, -32602, &format!("No {kind} object named '{name}'")),
+            },
+            None => JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id,
+                result: Some(json!({
+                    "content": [{
+                        "type": "text",
+                        "text": format!(
+                            "Found {} {kind}.\n\n{}",
+                            objects.len(),
+                            serde_json::to_string_pretty(&objects).unwrap_or_default()
+                        )
+                    }],
+                    "objects": objects
+                })),
+                error: None,
+            },
+        },
+        Err(e) => {
+            error!("Database error getting {kind}: {e}");
+            create_error_response(id, -32603, &format!("Failed to get {kind}: {e}"))
+        }
+    }
+}
+
+async fn search_schema(id: Option<Value>, database: &str, query: &str, limit: usize, pool: &Pool<MySql>) -> JsonRpcResponse {
+    debug!("Searching schema for: {query}");
+
+    if query.trim().is_empty() {
+        return create_error_response(id, -32602, "Search query is empty");
+    }
+
+    match search::search_schema(pool, database, query, limit).await {
+        Ok(matches) => {
+            let lines: Vec<String> = matches
+                .iter()
+                .map(|m| {
+                    let comment = m["comment"].as_str().filter(|c| !c.is_empty()).map(|c| format!(" -- {c}"));
+                    match m["column"].as_str() {
+                        Some(column) => format!(
+                            "{}.{column} {}{}",
+                            m["table"].as_str().unwrap_or_default(),
+                            m["column_type"].as_str().unwrap_or_default(),
+                            comment.unwrap_or_default()
+                        ),
+                        None => format!(
+                            "{} ({}){}",
+                            m["table"].as_str().unwrap_or_default(),
+                            m["table_type"].as_str().unwrap_or_default(),
+                            comment.unwrap_or_default()
+                        ),
+                    }
+                })
+                .collect();
+            JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id,
+                result: Some(json!({
+                    "content": [{
+                        "type": "text",
+                        "text": format!("Found {} matches for '{query}'.\n\n{}", matches.len(), lines.join("\n"))
+                    }],
+                    "matches": matches
+                })),
+                error: None,
+            }
+        }
+        Err(e) => {
+            error!("Database error searching schema: {e}");
+            create_error_response(id, -32603, &format!("Failed to search schema: {e}"))
+        }
+    }
+}
+
+async fn get_er_diagram(
+    id: Option<Value>,
+    database: &str,
+    diagram_args: &DiagramArguments,
+    pool: &Pool<MySql>,
+    ctx: &RequestContext,
+) -> JsonRpcResponse {
+    let format = diagram_args.format.as_str();
+    debug!("Drawing {format} diagram of {:?} ({} hops)", diagram_args.tables, diagram_args.hops);
+
+    if !diagram::FORMATS.contains(&format) {
+        return create_error_response(
+            id,
+            -32602,
+            &format!("Unknown diagram format '{format}', expected one of: {}", diagram::FORMATS.join(", ")),
+        );
+    }
+
+    let schemas = match &diagram_args.tables {
+        Some(tables) if !tables.is_empty() => {
+            diagram::neighbourhood(pool, database, tables, diagram_args.hops, ctx).await
+        }
+        _ => get_all_table_schemas(pool, database, ctx).await,
+    };
+    let schemas = match schemas {
+        Ok(schemas) => schemas,
+        Err(e) => {
+            error!("Database error drawing diagram: {e}");
+            return create_error_response(id, e.code(), &format!("Failed to draw diagram: {e}"));
+        }
+    };
+
+    let diagram = match format {
+        "dot" => diagram::dot(&schemas),
+        _ => diagram::mermaid(&schemas),
+    };
+    info!("Drew {format} diagram of {} tables", schemas.len());
+    JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id,
+        result: Some(json!({
+            "content": [{
+                "type": "text",
+                "text": format!("```{format}\n{diagram}\n```")
+            }],
+            "diagram": diagram
+        })),
+        error: None,
+    }
+}
+
+async fn get_schema_snapshot(
+    id: Option<Value>,
+    database: &str,
+    pool: &Pool<MySql>,
+    ctx: &RequestContext,
+) -> JsonRpcResponse {
+    debug!("Taking schema snapshot of {database}");
+
+    match snapshot::take(pool, database, ctx).await {
+        Ok(snapshot) => {
+            info!("Took schema snapshot of {database}");
+            JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id,
+                result: Some(json!({
+                    "content": [{
+                        "type": "text",
+                        "text": serde_json::to_string_pretty(&snapshot).unwrap_or_default()
+                    }],
+                    "snapshot": snapshot
+                })),
+                error: None,
+            }
+        }
+        Err(e) => {
+            error!("Database error taking schema snapshot: {e}");
+            create_error_response(id, e.code(), &format!("Failed to take schema snapshot: {e}"))
+        }
+    }
+}
+
+async fn get_schema_diff(
+    id: Option<Value>,
+    database: &str,
+    diff_args: &DiffArguments,
+    snapshot_dir: Option<&Path>,
+    pool: &Pool<MySql>,
+    ctx: &RequestContext,
+) -> JsonRpcResponse {
+    let from = match snapshot::load(&diff_args.from, snapshot_dir) {
+        Ok(from) => from,
+        Err(message) => return create_error_response(id, -32602, &format!("Invalid 'from' snapshot: {message}")),
+    };
+    let to = match &diff_args.to {
+        Some(to) => match snapshot::load(to, snapshot_dir) {
+            Ok(to) => to,
+            Err(message) => return create_error_response(id, -32602, &format!("Invalid 'to' snapshot: {message}")),
+        },
+        None => match snapshot::take(pool, database, ctx).await {
+            Ok(to) => to,
+            Err(e) => {
+                error!("Database error taking schema snapshot: {e}");
+                return create_error_response(id, e.code(), &format!("Failed to read the live schema: {e}"));
+            }
+        },
+    };
+
+    let changes = snapshot::diff(&from, &to);
+    let label = |snapshot: &Value| {
+        format!(
+            "'{}' at {}",
+            snapshot["database"].as_str().unwrap_or_default(),
+            snapshot["created_at"].as_str().unwrap_or_default()
+        )
+    };
+    let mut text = format!("{} changes from {} to {}.", changes.len(), label(&from), label(&to));
+    if !changes.is_empty() {
+        let lines: Vec<String> = changes.iter().map(snapshot::describe).collect();
+        text.push_str(&format!("\n\n{}", lines.join("\n")));
+    }
+    JsonRpcResponse {
+        jsonrpc: "2.0".to_string(),
+        id,
+        result: Some(json!({
+            "content": [{
+                "type": "text",
+                "text": text
+            }],
+            "changes": changes
+        })),
+        error: None,
+    }
+}
+
+async fn get_table_profile(
+    id: Option<Value>,
+    database: &str,
+    profile_args: &ProfileArguments,
+    args: &Args,
+    pool: &Pool<MySql>,
+    ctx: &RequestContext,
+) -> JsonRpcResponse {
+    let table_name = profile_args.table_name.as_str();
+    debug!("Profiling table: {table_name}");
+
+    // The tool may lower the scan budget and sample size, but not raise them past the server's limits
+    let options = profile::ProfileOptions {
+        sample_rows: profile_args.sample_rows.min(args.max_rows),
+        top_values: profile_args.top_values,
+        scan_rows: profile_args.scan_rows.unwrap_or(args.profile_scan_rows).clamp(1, args.profile_scan_rows.max(1)),
+        timeout: Some(Duration::from_secs(args.query_timeout)).filter(|timeout| !timeout.is_zero()),
+    };
+    match profile::profile_table(pool, database, table_name, &options, ctx).await {
+        Ok(profile) => {
+            info!("Profiled {} rows of table '{table_name}'", profile["rows_scanned"]);
+            let mut text = profile::describe(&profile);
+            if profile["sample_rows"].as_array().is_some_and(|rows| !rows.is_empty()) {
+                text.push_str(&format!(
+                    "\n\nSample rows:\n{}",
+                    serde_json::to_string_pretty(&profile["sample_rows"]).unwrap_or_default()
+                ));
+            }
+            JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id,
+                result: Some(json!({
+                    "content": [{
+                        "type": "text",
+                        "text": text
+                    }],
+                    "profile": profile
+                })),
+                error: None,
+            }
+        }
+        Err(e) => {
+            error!("Database error profiling table '{table_name}': {e}");
+            create_error_response(id, e.code(), &format!("Failed to profile table '{table_name}': {e}"))
+        }
+    }
+}
+
+async fn get_ddl(
+    id: Option<Value>,
+    database: &str,
+    kind: Option<&str>,
+    name: Option<&str>,
+    pool: &Pool<MySql>,
+    ctx: &RequestContext,
+) -> JsonRpcResponse {
+    debug!("Getting DDL: kind={kind:?}, name={name:?}");
+
+    let Some(name) = name else {
+        return match ddl::export_database(pool, database, ctx).await {
+            Ok(objects) => {
+                info!("Exported DDL of {} objects", objects.len());
+                let statements: Vec<String> = objects
+                    .iter()
+                    .map(|object| match object["ddl"].as_str() {
+                        Some(ddl) => format!("{ddl};"),
+                        None => format!(
+                            "-- Definition of {} '{}' is not visible to this user",
+                            object["kind"].as_str().unwrap_or_default(),
+                            object["name"].as_str().unwrap_or_default()
+                        ),
+                    })
+                    .collect();
                 JsonRpcResponse {
                     jsonrpc: "2.0".to_string(),
                     id,
                     result: Some(json!({
                         "content": [{
                             "type": "text",
-                            "text": format!("Retrieved schema for table '{}'.", table_name)
+                            "text": statements.join("\n\n")
                         }],
-                        "schema": schema
+                        "objects": objects
                     })),
                     error: None,
                 }
             }
             Err(e) => {
-                error!("Database error getting schema for table '{table_name}': {e}");
-                JsonRpcResponse {
-                    jsonrpc: "2.0".to_string(),
-                    id,
-                    result: None,
-                    error: Some(JsonRpcError {
-                        code: -32603,
-                        message: format!("Failed to get schema for table '{table_name}': {e}"),
-                        data: None,
-                    }),
-                }
+                error!("Database error exporting DDL: {e}");
+                create_error_response(id, e.code(), &format!("Failed to export DDL: {e}"))
             }
+        };
+    };
+
+    let kind = match kind {
+        Some(kind) if ddl::KINDS.contains(&kind) => kind,
+        Some(kind) => {
+            return create_error_response(
+                id,
+                -32602,
+                &format!("Unknown object kind '{kind}', expected one of: {}", ddl::KINDS.join(", ")),
+            );
+        }
+        None => return create_error_response(id, -32602, "kind is required when name is given"),
+    };
+
+    match ddl::show_create(pool, database, kind, name).await {
+        Ok(Some(ddl)) => JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: Some(json!({
+                "content": [{
+                    "type": "text",
+                    "text": format!("{ddl};")
+                }],
+                "ddl": ddl
+            })),
+            error: None,
+        },
+        Ok(None) => create_error_response(
+            id,
+            -32603,
+            &format!("Definition of {kind} '{name}' is not visible to this user"),
+        ),
+        Err(e) if is_missing_object(&e) => create_error_response(id, -32602, &format!("No {kind} named '{name}'")),
+        Err(e) => {
+            error!("Database error getting DDL of {kind} '{name}': {e}");
+            create_error_response(id, -32603, &format!("Failed to get DDL of {kind} '{name}': {e}"))
         }
     }
 }
@@ -943,171 +2835,1246 @@ async fn delete_data(
 
 async fn execute_query(
     id: serde_json::Value,
-    query: String,
+    cursor: QueryCursor,
     pool: &Pool<MySql>,
     allow_dangerous_queries: bool,
+    limits: QueryLimits,
+    ctx: &RequestContext,
 ) -> JsonRpcResponse {
+    let offset = cursor.offset;
     // Validate queries unless dangerous queries are allowed
     if !allow_dangerous_queries {
-        // Basic validation - only allow SELECT queries
-        let trimmed_query = query.trim();
-        if !trimmed_query.to_uppercase().starts_with("SELECT") {
-            return create_error_response(Some(id), -32602, "Only SELECT queries are allowed. Use --allow-dangerous-queries flag to execute other query types.");
-        }
-        
-        // Check for potentially dangerous keywords
-        let dangerous_keywords = ["INSERT", "UPDATE", "DELETE", "DROP", "CREATE", "ALTER", "TRUNCATE", "GRANT", "REVOKE"];
-        let query_upper = trimmed_query.to_uppercase();
-        for keyword in &dangerous_keywords {
-            if query_upper.contains(keyword) {
-                return create_error_response(Some(id), -32602, &format!("Query contains forbidden keyword: {}. Use --allow-dangerous-queries flag to allow such queries.", keyword));
-            }
+        if let Err(reason) = sql_guard::check_read_only(&cursor.query) {
+            return create_error_response(Some(id), -32602, &format!("{reason}. Only read-only queries are allowed. Use --allow-dangerous-queries flag to execute other query types."));
         }
     }
 
-    debug!("Executing query: {}", query);
-    
-    match sqlx::query(&query).fetch_all(pool).await {
-        Ok(rows) => {
-            let mut results = Vec::new();
-            
-            for row in rows {
-                let mut row_data = serde_json::Map::new();
-                
-                // Get column names and values
-                for (i, column) in row.columns().iter().enumerate() {
-                    let column_name = column.name();
-                    
-                    // Try to extract value as different types
-                    if let Ok(value) = row.try_get::<Option<String>, _>(i) {
-                        row_data.insert(column_name.to_string(), json!(value));
-                    } else if let Ok(value) = row.try_get::<Option<i64>, _>(i) {
-                        row_data.insert(column_name.to_string(), json!(value));
-                    } else if let Ok(value) = row.try_get::<Option<f64>, _>(i) {
-                        row_data.insert(column_name.to_string(), json!(value));
-                    } else if let Ok(value) = row.try_get::<Option<bool>, _>(i) {
-                        row_data.insert(column_name.to_string(), json!(value));
-                    } else {
-                        // Default to null if we can't determine the type
-                        row_data.insert(column_name.to_string(), json!(null));
+    debug!("Executing query: {}", cursor.query);
+
+    // Without --allow-dangerous-queries MySQL enforces read-only as well, in case a query gets past the parser
+    match fetch_page(pool, &cursor, limits, !allow_dangerous_queries, &ctx.cancel).await {
+        Ok(page) => {
+            if sql_guard::is_schema_change(&cursor.query) {
+                ctx.schema_cache.invalidate();
+                // Re-read the tables behind the resource list so the client hears about created or dropped ones
+                if let Some(database) = pool.connect_options().get_database() {
+                    if let Err(e) = cached_table_names(pool, database, ctx).await {
+                        warn!("Failed to refresh table names after a schema change: {e}");
                     }
                 }
-                
-                results.push(json!(row_data));
             }
-            
+            let results = page.rows;
+
             // Format results as a text table for better AI visibility
-            let mut content_text = format!("Query executed successfully. Retrieved {} rows.\n\n", results.len());
+            let mut content_text = if offset == 0 {
+                format!("Query executed successfully. Retrieved {} rows.\n\n", results.len())
+            } else {
+                format!("Query executed successfully. Retrieved {} rows starting at row {}.\n\n", results.len(), offset + 1)
+            };
             
             if !results.is_empty() {
                 // Convert results to a formatted string
                 content_text.push_str("Results:\n");
                 content_text.push_str(&serde_json::to_string_pretty(&results).unwrap_or_else(|_| "Error formatting results".to_string()));
             }
+
+            // Each page re-runs the query, so only ORDER BY keeps the pages consistent
+            let paged = page.truncated || offset > 0;
+            let warning = (paged && sql_guard::is_unordered_select(&cursor.query)).then_some(
+                "The query has no ORDER BY, so pages may repeat or skip rows. Add an ORDER BY on a unique key to page reliably.",
+            );
+            let next_cursor = page.truncated.then(|| {
+                encode_cursor(&QueryCursor {
+                    offset: offset + results.len(),
+                    ..cursor
+                })
+            });
+            if let Some(next_cursor) = &next_cursor {
+                content_text.push_str(&format!(
+                    "\n\nResult truncated after {} rows. Call the query tool with cursor \"{next_cursor}\" to get the next page.",
+                    results.len()
+                ));
+            }
+            if let Some(warning) = warning {
+                content_text.push_str(&format!("\n\n{warning}"));
+            }
+
+            let mut result = json!({
+                "content": [{
+                    "type": "text",
+                    "text": content_text
+                }],
+                "truncated": page.truncated
+            });
+            if let Some(next_cursor) = next_cursor {
+                result["next_cursor"] = json!(next_cursor);
+            }
+            if let Some(warning) = warning {
+                result["warning"] = json!(warning);
+            }
             
             JsonRpcResponse {
                 jsonrpc: "2.0".to_string(),
                 id: Some(id),
-                result: Some(json!({
-                    "content": [{
-                        "type": "text",
-                        "text": content_text
-                    }]
-                })),
+                result: Some(result),
                 error: None,
             }
         }
-        Err(e) => {
+        Err(QueryError::Cancelled) => {
+            info!("Query cancelled by client");
+            create_error_response(Some(id), -32800, "Request cancelled")
+        }
+        Err(QueryError::TimedOut(timeout)) => {
+            warn!("Query timed out after {}ms", timeout.as_millis());
+            create_error_response(Some(id), -32006, &format!("Query timed out after {}ms and was cancelled", timeout.as_millis()))
+        }
+        Err(QueryError::Database(e)) if is_read_only_violation(&e) => {
+            warn!("Query refused by read-only transaction: {}", e);
+            create_error_response(Some(id), -32005, &format!("Query refused by read-only transaction: {}", e))
+        }
+        Err(QueryError::Database(e)) => {
             error!("Query execution failed: {}", e);
             create_error_response(Some(id), -32004, &format!("Query execution failed: {}", e))
         }
     }
 }
 
-async fn get_table_schema(pool: &Pool<MySql>, table_name: &str) -> Result<Value, sqlx::Error> {
-    let current_db: Option<String> = sqlx::query_scalar("SELECT DATABASE()").fetch_optional(pool).await?;
-    if current_db.is_none() {
-        return Err(sqlx::Error::Configuration("No database selected. Please specify a database to use.".into()));
+// Run a query on a dedicated connection and collect one page of its rows. Read-only queries
+// run inside a read-only transaction that is always rolled back. SELECTs are bounded by
+// MAX_EXECUTION_TIME, and a statement still running at the deadline or when the request
+// is cancelled is killed.
+async fn fetch_page(
+    pool: &Pool<MySql>,
+    cursor: &QueryCursor,
+    limits: QueryLimits,
+    read_only: bool,
+    cancel: &CancellationToken,
+) -> Result<QueryPage, QueryError> {
+    run_detached(run_page(pool.clone(), cursor.clone(), limits, read_only, cancel.clone())).await
+}
+
+async fn run_page(
+    pool: Pool<MySql>,
+    cursor: QueryCursor,
+    limits: QueryLimits,
+    read_only: bool,
+    cancel: CancellationToken,
+) -> Result<QueryPage, QueryError> {
+    let timeout = limits.timeout;
+    let mut conn = pool.acquire().await?;
+    let connection_id = sqlx::query_scalar::<_, u64>("SELECT CONNECTION_ID()").fetch_one(&mut *conn).await?;
+    let query = cursor.query.as_str();
+
+    if let Some(database) = &cursor.database {
+        // Pooled connections are expected to use the configured database, so don't reuse this one
+        conn.close_on_drop();
+        let statement = format!("USE {}", quote_identifier(database));
+        conn.execute(statement.as_str()).await?;
     }
-    let current_db = current_db.unwrap();
-    
-    // Get table information
-    let table_info_query = format!("SELECT * FROM information_schema.tables WHERE table_name = '{table_name}' AND table_schema = '{current_db}'");
-    let table_info = sqlx::query(&table_info_query).fetch_optional(pool).await?;
-    
-    if table_info.is_none() {
-        return Err(sqlx::Error::RowNotFound);
+
+    if read_only {
+        conn.execute("START TRANSACTION READ ONLY").await?;
     }
-    
-    // Get column information
-    let columns_query = format!(
-        "SELECT column_name, data_type, is_nullable, column_default, column_key, extra, column_comment 
-         FROM information_schema.columns 
-         WHERE table_name = '{table_name}' AND table_schema = '{current_db}' 
-         ORDER BY ordinal_position"
+
+    // Set last, so no early return can hand the connection back with the session timeout still set
+    let mut max_execution_time_set = false;
+    if let Some(timeout) = timeout.filter(|_| sql_guard::is_select(query)) {
+        let statement = format!("SET SESSION max_execution_time = {}", timeout.as_millis());
+        match conn.execute(statement.as_str()).await {
+            Ok(_) => max_execution_time_set = true,
+            // MariaDB has no max_execution_time, the KILL QUERY fallback still applies
+            Err(e) => debug!("Could not set max_execution_time: {e}"),
+        }
+    }
+
+    let started = tokio::time::Instant::now();
+    let finished = interruptible(fetch_rows(&mut conn, query, cursor.offset, limits), started, timeout, &cancel).await;
+    let result = match finished {
+        Ok(Err(e)) if is_execution_timeout(&e) => Err(QueryError::TimedOut(timeout.unwrap_or_default())),
+        Ok(result) => result.map_err(QueryError::from),
+        Err(interrupted) => {
+            abort_statement(&pool, &mut conn, connection_id).await;
+            return Err(interrupted);
+        }
+    };
+
+    if matches!(result, Ok(QueryPage { truncated: true, .. })) {
+        // The rest of the result is still in flight, closing the connection is cheaper than
+        // draining it and also ends the read-only transaction
+        conn.close_on_drop();
+        return result;
+    }
+
+    if read_only {
+        if let Err(e) = conn.execute("ROLLBACK").await {
+            warn!("Failed to roll back read-only transaction: {e}");
+            // Don't return a connection with an open transaction to the pool
+            conn.close_on_drop();
+            return result;
+        }
+    }
+
+    if max_execution_time_set {
+        if let Err(e) = conn.execute("SET SESSION max_execution_time = DEFAULT").await {
+            warn!("Failed to reset max_execution_time: {e}");
+            conn.close_on_drop();
+        }
+    }
+
+    result
+}
+
+// A cancelled request stops waiting for its handler, dropping it wherever it was. Work that has
+// to clean up after itself, killing its statement and closing its connection, runs in a task of
+// its own that outlives the handler.
+async fn run_detached<T: Send + 'static>(task: impl std::future::Future<Output = T> + Send + 'static) -> T {
+    tokio::spawn(task).await.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+}
+
+// Waits for `statement` until `timeout` has passed since `started` or the request is cancelled.
+// An interrupted statement keeps running on the server, so callers pass it to `abort_statement`.
+async fn interruptible<T>(
+    statement: impl std::future::Future<Output = T>,
+    started: tokio::time::Instant,
+    timeout: Option<Duration>,
+    cancel: &CancellationToken,
+) -> Result<T, QueryError> {
+    let deadline = async {
+        match timeout {
+            Some(timeout) => {
+                tokio::time::sleep_until(started + timeout).await;
+                timeout
+            }
+            None => std::future::pending().await,
+        }
+    };
+    tokio::select! {
+        result = statement => Ok(result),
+        timeout = deadline => Err(QueryError::TimedOut(timeout)),
+        _ = cancel.cancelled() => Err(QueryError::Cancelled),
+    }
+}
+
+// Kill a statement `interruptible` stopped waiting for from another connection. Its own
+// connection was left mid-statement, so it is closed rather than returned to the pool.
+async fn abort_statement(pool: &Pool<MySql>, conn: &mut PoolConnection<MySql>, connection_id: u64) {
+    info!("Killing query on connection {connection_id}");
+    if let Err(e) = sqlx::raw_sql(&format!("KILL QUERY {connection_id}")).execute(pool).await {
+        error!("Failed to kill query on connection {connection_id}: {e}");
+    }
+    conn.close_on_drop();
+}
+
+// Stream rows, skipping `offset` rows, until the result ends or a limit is reached
+async fn fetch_rows(
+    conn: &mut MySqlConnection,
+    query: &str,
+    offset: usize,
+    limits: QueryLimits,
+) -> Result<QueryPage, sqlx::Error> {
+    let mut stream = sqlx::query(query).fetch(conn);
+    let mut rows = Vec::new();
+    let mut bytes = 0;
+    let mut skipped = 0;
+
+    while let Some(row) = stream.try_next().await? {
+        if skipped < offset {
+            skipped += 1;
+            continue;
+        }
+        if rows.len() >= limits.max_rows {
+            return Ok(QueryPage { rows, truncated: true });
+        }
+
+        let value = json!(decode::row_to_json(&row));
+        let size = value.to_string().len();
+        // Always return at least one row so paging makes progress
+        if !rows.is_empty() && bytes + size > limits.max_bytes {
+            return Ok(QueryPage { rows, truncated: true });
+        }
+        bytes += size;
+        rows.push(value);
+    }
+
+    Ok(QueryPage { rows, truncated: false })
+}
+
+fn encode_cursor(cursor: &QueryCursor) -> String {
+    URL_SAFE_NO_PAD.encode(json!(cursor).to_string())
+}
+
+fn decode_cursor(cursor: &str) -> Option<QueryCursor> {
+    let bytes = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+// ER_QUERY_TIMEOUT (MySQL) or ER_STATEMENT_TIMEOUT (MariaDB)
+fn is_execution_timeout(error: &sqlx::Error) -> bool {
+    error
+        .as_database_error()
+        .and_then(|e| e.try_downcast_ref::<MySqlDatabaseError>())
+        .is_some_and(|e| matches!(e.number(), 3024 | 1969))
+}
+
+// ER_CANT_EXECUTE_IN_READ_ONLY_TRANSACTION
+fn is_read_only_violation(error: &sqlx::Error) -> bool {
+    error
+        .as_database_error()
+        .and_then(|e| e.try_downcast_ref::<MySqlDatabaseError>())
+        .is_some_and(|e| e.number() == 1792)
+}
+
+// ER_NO_SUCH_TABLE, ER_SP_DOES_NOT_EXIST and ER_TRG_DOES_NOT_EXIST
+fn is_missing_object(error: &sqlx::Error) -> bool {
+    error
+        .as_database_error()
+        .and_then(|e| e.try_downcast_ref::<MySqlDatabaseError>())
+        .is_some_and(|e| matches!(e.number(), 1146 | 1305 | 1360))
+}
+
+// Backtick-quotes an identifier for use in statements that cannot take bound parameters
+fn quote_identifier(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
+}
+
+async fn get_table_schema(pool: &Pool<MySql>, database: &str, table_name: &str) -> Result<Value, SchemaError> {
+    read_table_schemas(pool, database, Some(table_name), None)
+        .await?
+        .pop()
+        .ok_or_else(|| SchemaError::TableNotFound {
+            database: database.to_string(),
+            table_name: table_name.to_string(),
+        })
+}
+
+// Schemas of every base table in `database`, or of the one table or view `table_name`. Each kind
+// of metadata is read for all the tables in one information_schema query and grouped by table
+// here, so a whole database takes the same six round trips as a single table. A cancelled read
+// stops between them.
+async fn read_table_schemas(
+    pool: &Pool<MySql>,
+    database: &str,
+    table_name: Option<&str>,
+    ctx: Option<&RequestContext>,
+) -> Result<Vec<Value>, SchemaError> {
+    const STEPS: usize = 6;
+    let report_progress = |step: usize, message: &str| {
+        if let Some(ctx) = ctx {
+            ctx.report_progress(step, STEPS, message);
+        }
+    };
+    let cancelled = || ctx.is_some_and(RequestContext::is_cancelled);
+    // Narrows a query to `table_name` if there is one
+    let table_filter = |column: &str| match table_name {
+        Some(_) => format!(" AND {column} = ?"),
+        None => String::new(),
+    };
+
+    report_progress(0, "Reading tables");
+    let tables_filter = match table_name {
+        Some(_) => table_filter("table_name"),
+        None => " AND table_type = 'BASE TABLE'".to_string(),
+    };
+    let tables = fetch_table_rows(
+        pool,
+        &format!(
+            "SELECT table_name AS table_name, engine AS engine, table_rows AS table_rows,
+                    data_length AS data_length, index_length AS index_length, auto_increment AS auto_increment,
+                    CAST(create_time AS CHAR) AS create_time, CAST(update_time AS CHAR) AS update_time,
+                    table_collation AS table_collation, table_comment AS table_comment
+             FROM information_schema.tables
+             WHERE table_schema = ?{tables_filter}
+             ORDER BY table_name"
+        ),
+        database,
+        table_name,
+    )
+    .await?;
+    if tables.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    if cancelled() {
+        return Err(SchemaError::Cancelled);
+    }
+    report_progress(1, "Reading columns");
+    let rows = fetch_table_rows(
+        pool,
+        &format!(
+            "SELECT table_name AS table_name, column_name AS column_name, data_type AS data_type,
+                    column_type AS column_type, is_nullable AS is_nullable, column_default AS column_default,
+                    column_key AS column_key, extra AS extra, column_comment AS column_comment,
+                    character_set_name AS character_set_name, collation_name AS collation_name,
+                    numeric_precision AS numeric_precision, numeric_scale AS numeric_scale,
+                    datetime_precision AS datetime_precision, generation_expression AS generation_expression
+             FROM information_schema.columns
+             WHERE table_schema = ?{}
+             ORDER BY table_name, ordinal_position",
+            table_filter("table_name")
+        ),
+        database,
+        table_name,
+    )
+    .await?;
+    let mut columns: HashMap<String, Vec<Value>> = HashMap::new();
+    for row in rows {
+        columns
+            .entry(row.try_get::<String, _>("table_name").unwrap_or_default())
+            .or_default()
+            .push(column_info(&row));
+    }
+
+    if cancelled() {
+        return Err(SchemaError::Cancelled);
+    }
+    report_progress(2, "Reading indexes");
+    // Which columns STATISTICS has differs between MySQL and MariaDB versions, so select them
+    // all and read them through the generic decoder. PRIMARY comes first, as in SHOW INDEX.
+    let rows = fetch_table_rows(
+        pool,
+        &format!(
+            "SELECT * FROM information_schema.statistics
+             WHERE table_schema = ?{}
+             ORDER BY table_name, index_name <> 'PRIMARY', index_name, seq_in_index",
+            table_filter("table_name")
+        ),
+        database,
+        table_name,
+    )
+    .await?;
+    let mut index_rows: HashMap<String, Vec<Map<String, Value>>> = HashMap::new();
+    for row in &rows {
+        let row: Map<String, Value> = decode::row_to_json(row)
+            .into_iter()
+            .map(|(column, value)| (column.to_uppercase(), value))
+            .collect();
+        let table = row.get("TABLE_NAME").and_then(Value::as_str).unwrap_or_default().to_string();
+        index_rows.entry(table).or_default().push(row);
+    }
+
+    if cancelled() {
+        return Err(SchemaError::Cancelled);
+    }
+    report_progress(3, "Reading check constraints");
+    let mut check_constraints = group_by_table(get_check_constraints(pool, database, table_name).await?, "table");
+
+    if cancelled() {
+        return Err(SchemaError::Cancelled);
+    }
+    report_progress(4, "Reading foreign keys");
+    let mut foreign_keys = group_by_table(
+        get_foreign_keys(
+            pool,
+            &format!(
+                "kcu.table_schema = ? AND kcu.referenced_table_name IS NOT NULL{}",
+                table_filter("kcu.table_name")
+            ),
+            database,
+            table_name,
+        )
+        .await?,
+        "table",
     );
-    let columns = sqlx::query(&columns_query).fetch_all(pool).await?;
-    
-    // Get indexes
-    let indexes_query = format!("SHOW INDEX FROM `{current_db}`.`{table_name}`");
-    let indexes = sqlx::query(&indexes_query).fetch_all(pool).await?;
-    
-    let column_info: Vec<Value> = columns
+
+    if cancelled() {
+        return Err(SchemaError::Cancelled);
+    }
+    report_progress(5, "Reading incoming foreign keys");
+    let mut referenced_by = group_by_table(
+        get_foreign_keys(
+            pool,
+            &format!("kcu.referenced_table_schema = ?{}", table_filter("kcu.referenced_table_name")),
+            database,
+            table_name,
+        )
+        .await?,
+        "referenced_table",
+    );
+    report_progress(STEPS, &format!("Read {} table schemas", tables.len()));
+
+    Ok(tables
         .into_iter()
-        .map(|row| {
+        .map(|table_info| {
+            let table = table_info.try_get::<String, _>("table_name").unwrap_or_default();
             json!({
-                "name": row.try_get::<String, _>("column_name").unwrap_or_default(),
-                "type": row.try_get::<String, _>("data_type").unwrap_or_default(),
-                "nullable": row.try_get::<String, _>("is_nullable").unwrap_or_default() == "YES",
-                "default": row.try_get::<Option<String>, _>("column_default").unwrap_or_default(),
-                "key": row.try_get::<String, _>("column_key").unwrap_or_default(),
-                "extra": row.try_get::<String, _>("extra").unwrap_or_default(),
-                "comment": row.try_get::<String, _>("column_comment").unwrap_or_default(),
+                "database": database,
+                "table_name": table,
+                "engine": table_info.try_get::<Option<String>, _>("engine").unwrap_or_default(),
+                "collation": table_info.try_get::<Option<String>, _>("table_collation").unwrap_or_default(),
+                "comment": table_info.try_get::<Option<String>, _>("table_comment").unwrap_or_default(),
+                // Sizes come from the storage engine's statistics, which MySQL 8 caches for
+                // information_schema_stats_expiry seconds; InnoDB row counts are estimates
+                "statistics": {
+                    "estimated_rows": table_info.try_get::<Option<u64>, _>("table_rows").unwrap_or_default(),
+                    "data_length": table_info.try_get::<Option<u64>, _>("data_length").unwrap_or_default(),
+                    "index_length": table_info.try_get::<Option<u64>, _>("index_length").unwrap_or_default(),
+                    "auto_increment": table_info.try_get::<Option<u64>, _>("auto_increment").unwrap_or_default(),
+                    "create_time": table_info.try_get::<Option<String>, _>("create_time").unwrap_or_default(),
+                    "update_time": table_info.try_get::<Option<String>, _>("update_time").unwrap_or_default(),
+                },
+                "columns": columns.remove(&table).unwrap_or_default(),
+                "indexes": group_index_rows(&index_rows.remove(&table).unwrap_or_default()),
+                "check_constraints": check_constraints.remove(&table).unwrap_or_default(),
+                "foreign_keys": foreign_keys.remove(&table).unwrap_or_default(),
+                "referenced_by": referenced_by.remove(&table).unwrap_or_default()
             })
         })
-        .collect();
-    
-    let index_info: Vec<Value> = indexes
+        .collect())
+}
+
+// Runs a query binding `database`, then `table_name` if the query was narrowed to one table
+async fn fetch_table_rows(
+    pool: &Pool<MySql>,
+    query: &str,
+    database: &str,
+    table_name: Option<&str>,
+) -> Result<Vec<MySqlRow>, sqlx::Error> {
+    let mut query = sqlx::query(query).bind(database);
+    if let Some(table_name) = table_name {
+        query = query.bind(table_name);
+    }
+    query.fetch_all(pool).await
+}
+
+// Splits `items` by the table named in their `key` field, keeping their order
+fn group_by_table(items: Vec<Value>, key: &str) -> HashMap<String, Vec<Value>> {
+    let mut grouped: HashMap<String, Vec<Value>> = HashMap::new();
+    for item in items {
+        let table = item[key].as_str().unwrap_or_default().to_string();
+        grouped.entry(table).or_default().push(item);
+    }
+    grouped
+}
+
+fn column_info(row: &MySqlRow) -> Value {
+    let column_type = row.try_get::<String, _>("column_type").unwrap_or_default();
+    // Empty for ordinary columns
+    let generation_expression = row
+        .try_get::<Option<String>, _>("generation_expression")
+        .unwrap_or_default()
+        .filter(|expression| !expression.is_empty());
+    let mut column = json!({
+        "name": row.try_get::<String, _>("column_name").unwrap_or_default(),
+        "type": row.try_get::<String, _>("data_type").unwrap_or_default(),
+        "column_type": column_type,
+        "nullable": row.try_get::<String, _>("is_nullable").unwrap_or_default() == "YES",
+        "default": row.try_get::<Option<String>, _>("column_default").unwrap_or_default(),
+        "key": row.try_get::<String, _>("column_key").unwrap_or_default(),
+        "extra": row.try_get::<String, _>("extra").unwrap_or_default(),
+        "comment": row.try_get::<String, _>("column_comment").unwrap_or_default(),
+        "character_set": row.try_get::<Option<String>, _>("character_set_name").unwrap_or_default(),
+        "collation": row.try_get::<Option<String>, _>("collation_name").unwrap_or_default(),
+        "numeric_precision": row.try_get::<Option<u64>, _>("numeric_precision").unwrap_or_default(),
+        "numeric_scale": row.try_get::<Option<u64>, _>("numeric_scale").unwrap_or_default(),
+        "datetime_precision": row.try_get::<Option<u64>, _>("datetime_precision").unwrap_or_default(),
+        "generation_expression": generation_expression,
+    });
+    if let Some(values) = enum_values(&column_type) {
+        column["values"] = json!(values);
+    }
+    column
+}
+
+// One entry per index with its columns in key order, from decoded STATISTICS rows of one table
+// with upper case column names
+fn group_index_rows(rows: &[Map<String, Value>]) -> Vec<Value> {
+    let mut indexes: Vec<Value> = Vec::new();
+    for row in rows {
+        let name = row.get("INDEX_NAME").cloned().unwrap_or(Value::Null);
+        let order = match row.get("COLLATION").and_then(Value::as_str) {
+            Some("A") => json!("ASC"),
+            Some("D") => json!("DESC"),
+            _ => Value::Null,
+        };
+        let column = json!({
+            "name": row.get("COLUMN_NAME").cloned().unwrap_or(Value::Null),
+            // Functional key parts (MySQL 8.0.13+) have an expression instead of a column
+            "expression": row.get("EXPRESSION").cloned().unwrap_or(Value::Null),
+            "sub_part": row.get("SUB_PART").cloned().unwrap_or(Value::Null),
+            "order": order,
+        });
+        // Cardinality counts distinct values of the key prefix, so the last column's is the index's
+        let cardinality = row.get("CARDINALITY").cloned().unwrap_or(Value::Null);
+
+        // Rows are ordered by index and then by SEQ_IN_INDEX
+        if let Some(last) = indexes.last_mut() {
+            if last["name"] == name {
+                last["columns"].as_array_mut().unwrap().push(column);
+                last["cardinality"] = cardinality;
+                continue;
+            }
+        }
+
+        let index_type = row.get("INDEX_TYPE").and_then(Value::as_str).unwrap_or_default().to_string();
+        // MySQL 8 reports IS_VISIBLE, MariaDB 10.6+ reports IGNORED
+        let visible = match (row.get("IS_VISIBLE"), row.get("IGNORED")) {
+            (Some(visible), _) => visible.as_str() != Some("NO"),
+            (None, Some(ignored)) => ignored.as_str() != Some("YES"),
+            (None, None) => true,
+        };
+        indexes.push(json!({
+            "name": name,
+            "columns": [column],
+            "primary": name == "PRIMARY",
+            "unique": row.get("NON_UNIQUE").and_then(Value::as_i64) == Some(0),
+            "fulltext": index_type == "FULLTEXT",
+            "spatial": index_type == "SPATIAL",
+            "type": index_type,
+            "cardinality": cardinality,
+            "visible": visible,
+            "comment": row.get("INDEX_COMMENT").cloned().unwrap_or(Value::Null),
+        }));
+    }
+    indexes
+}
+
+// Members of an ENUM or SET column type such as `enum('a','it''s')`, unquoted
+fn enum_values(column_type: &str) -> Option<Vec<String>> {
+    let members = column_type
+        .strip_prefix("enum(")
+        .or_else(|| column_type.strip_prefix("set("))?
+        .strip_suffix(')')?;
+
+    let mut values = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = members.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' if in_quotes && chars.peek() == Some(&'\'') => {
+                current.push('\'');
+                chars.next();
+            }
+            '\'' => {
+                if in_quotes {
+                    values.push(std::mem::take(&mut current));
+                }
+                in_quotes = !in_quotes;
+            }
+            '\\' if in_quotes => {
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+            }
+            _ if in_quotes => current.push(c),
+            _ => {}
+        }
+    }
+    Some(values)
+}
+
+// CHECK constraints of the tables in a schema, or of one table. CHECK_CONSTRAINTS only exists
+// on MySQL 8.0.16+ and MariaDB 10.2+, older servers report no constraints.
+async fn get_check_constraints(
+    pool: &Pool<MySql>,
+    schema: &str,
+    table_name: Option<&str>,
+) -> Result<Vec<Value>, sqlx::Error> {
+    let table_filter = if table_name.is_some() { " AND tc.table_name = ?" } else { "" };
+    let query = format!(
+        "SELECT tc.table_name AS table_name, cc.constraint_name AS constraint_name, cc.check_clause AS check_clause
+         FROM information_schema.table_constraints tc
+         JOIN information_schema.check_constraints cc
+           ON cc.constraint_schema = tc.constraint_schema
+          AND cc.constraint_name = tc.constraint_name
+         WHERE tc.table_schema = ?{table_filter} AND tc.constraint_type = 'CHECK'
+         ORDER BY tc.table_name, cc.constraint_name"
+    );
+    let rows = fetch_table_rows(pool, &query, schema, table_name).await;
+
+    let rows = match rows {
+        Ok(rows) => rows,
+        // ER_UNKNOWN_TABLE
+        Err(e)
+            if e
+                .as_database_error()
+                .and_then(|e| e.try_downcast_ref::<MySqlDatabaseError>())
+                .is_some_and(|e| e.number() == 1109) =>
+        {
+            debug!("CHECK_CONSTRAINTS is not available: {e}");
+            return Ok(Vec::new());
+        }
+        Err(e) => return Err(e),
+    };
+
+    Ok(rows
         .into_iter()
         .map(|row| {
             json!({
-                "name": row.try_get::<String, _>("Key_name").unwrap_or_default(),
-                "column": row.try_get::<String, _>("Column_name").unwrap_or_default(),
-                "unique": row.try_get::<i32, _>("Non_unique").unwrap_or(1) == 0,
-                "type": row.try_get::<String, _>("Index_type").unwrap_or_default(),
+                "table": row.try_get::<String, _>("table_name").unwrap_or_default(),
+                "name": row.try_get::<String, _>("constraint_name").unwrap_or_default(),
+                "clause": row.try_get::<String, _>("check_clause").unwrap_or_default(),
             })
         })
-        .collect();
-    
-    Ok(json!({
-        "table_name": table_name,
-        "columns": column_info,
-        "indexes": index_info
-    }))
+        .collect())
 }
 
-async fn get_all_table_schemas(pool: &Pool<MySql>) -> Result<Vec<Value>, sqlx::Error> {
-    let current_db: Option<String> = sqlx::query_scalar("SELECT DATABASE()").fetch_optional(pool).await?;
-    if current_db.is_none() {
-        return Err(sqlx::Error::Configuration("No database selected. Please specify a database to use.".into()));
+// Foreign keys matching `filter`, which binds a schema and, if one is given, a table name.
+// Composite keys are returned as one entry with their columns in key order.
+async fn get_foreign_keys(
+    pool: &Pool<MySql>,
+    filter: &str,
+    schema: &str,
+    table_name: Option<&str>,
+) -> Result<Vec<Value>, sqlx::Error> {
+    let query = format!(
+        "SELECT kcu.constraint_name AS constraint_name, kcu.table_schema AS table_schema,
+                kcu.table_name AS table_name, kcu.column_name AS column_name,
+                kcu.referenced_table_schema AS referenced_table_schema,
+                kcu.referenced_table_name AS referenced_table_name,
+                kcu.referenced_column_name AS referenced_column_name,
+                rc.update_rule AS update_rule, rc.delete_rule AS delete_rule
+         FROM information_schema.key_column_usage kcu
+         JOIN information_schema.referential_constraints rc
+           ON rc.constraint_schema = kcu.constraint_schema
+          AND rc.constraint_name = kcu.constraint_name
+          AND rc.table_name = kcu.table_name
+         WHERE {filter}
+         ORDER BY kcu.table_schema, kcu.table_name, kcu.constraint_name, kcu.ordinal_position"
+    );
+    let rows = fetch_table_rows(pool, &query, schema, table_name).await?;
+
+    let mut foreign_keys: Vec<Value> = Vec::new();
+    for row in rows {
+        let name = row.try_get::<String, _>("constraint_name").unwrap_or_default();
+        let schema = row.try_get::<String, _>("table_schema").unwrap_or_default();
+        let table = row.try_get::<String, _>("table_name").unwrap_or_default();
+        let column = row.try_get::<String, _>("column_name").unwrap_or_default();
+        let referenced_column = row.try_get::<String, _>("referenced_column_name").unwrap_or_default();
+
+        // Rows are ordered by constraint, so a composite key continues the previous entry
+        if let Some(last) = foreign_keys.last_mut() {
+            if last["name"] == name && last["schema"] == schema && last["table"] == table {
+                last["columns"].as_array_mut().unwrap().push(json!(column));
+                last["referenced_columns"].as_array_mut().unwrap().push(json!(referenced_column));
+                continue;
+            }
+        }
+        foreign_keys.push(json!({
+            "name": name,
+            "schema": schema,
+            "table": table,
+            "columns": [column],
+            "referenced_schema": row.try_get::<String, _>("referenced_table_schema").unwrap_or_default(),
+            "referenced_table": row.try_get::<String, _>("referenced_table_name").unwrap_or_default(),
+            "referenced_columns": [referenced_column],
+            "on_update": row.try_get::<String, _>("update_rule").unwrap_or_default(),
+            "on_delete": row.try_get::<String, _>("delete_rule").unwrap_or_default(),
+        }));
     }
-    let current_db = current_db.unwrap();
-    
-    // Get all tables in the current database
-    let tables_query = format!("SELECT table_name FROM information_schema.tables WHERE table_schema = '{current_db}' AND table_type = 'BASE TABLE'");
-    let tables = sqlx::query(&tables_query).fetch_all(pool).await?;
-    
-    let mut schemas = Vec::new();
-    for table_row in tables {
-        let table_name: String = table_row.try_get("table_name")?;
-        match get_table_schema(pool, &table_name).await {
-            Ok(schema) => schemas.push(schema),
-            Err(e) => {
-                eprintln!("Failed to get schema for table {table_name}: {e}");
-                // Continue with other tables
+    Ok(foreign_keys)
+}
+
+// Views in the current database. Definitions and columns are only included when describing
+// a single view.
+async fn get_views(pool: &Pool<MySql>, database: &str, name: Option<&str>) -> Result<Vec<Value>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT table_name AS table_name, view_definition AS view_definition, check_option AS check_option,
+                is_updatable AS is_updatable, definer AS definer, security_type AS security_type
+         FROM information_schema.views
+         WHERE table_schema = ? AND (? IS NULL OR table_name = ?)
+         ORDER BY table_name",
+    )
+    .bind(database)
+    .bind(name)
+    .bind(name)
+    .fetch_all(pool)
+    .await?;
+
+    let mut views = Vec::new();
+    for row in rows {
+        let view_name = row.try_get::<String, _>("table_name").unwrap_or_default();
+        let definition = row.try_get::<Option<String>, _>("view_definition").unwrap_or_default();
+        let mut view = json!({
+            "name": view_name,
+            "tables": view_tables(definition.as_deref().unwrap_or_default(), database),
+            "updatable": row.try_get::<String, _>("is_updatable").unwrap_or_default() == "YES",
+            "check_option": row.try_get::<String, _>("check_option").unwrap_or_default(),
+            "definer": row.try_get::<String, _>("definer").unwrap_or_default(),
+            "security_type": row.try_get::<String, _>("security_type").unwrap_or_default(),
+        });
+        if name.is_some() {
+            let columns = sqlx::query(
+                "SELECT column_name AS column_name, column_type AS column_type, is_nullable AS is_nullable
+                 FROM information_schema.columns
+                 WHERE table_schema = ? AND table_name = ?
+                 ORDER BY ordinal_position",
+            )
+            .bind(database)
+            .bind(&view_name)
+            .fetch_all(pool)
+            .await?;
+            view["definition"] = json!(definition);
+            view["columns"] = columns
+                .into_iter()
+                .map(|column| {
+                    json!({
+                        "name": column.try_get::<String, _>("column_name").unwrap_or_default(),
+                        "type": column.try_get::<String, _>("column_type").unwrap_or_default(),
+                        "nullable": column.try_get::<String, _>("is_nullable").unwrap_or_default() == "YES",
+                    })
+                })
+                .collect();
+        }
+        views.push(view);
+    }
+    Ok(views)
+}
+
+// Fully qualified `database.table` names of the tables and views a view selects from
+fn view_tables(definition: &str, database: &str) -> Vec<String> {
+    let dialect = sqlparser::dialect::MySqlDialect {};
+    let Ok(statements) = sqlparser::parser::Parser::parse_sql(&dialect, definition) else {
+        debug!("Could not parse view definition: {definition}");
+        return Vec::new();
+    };
+
+    let mut tables = std::collections::BTreeSet::new();
+    let _ = sqlparser::ast::visit_relations(&statements, |relation| {
+        let parts: Vec<&str> = relation.0.iter().map(|ident| ident.value.as_str()).collect();
+        match parts.as_slice() {
+            [table] => tables.insert(format!("{database}.{table}")),
+            _ => tables.insert(parts.join(".")),
+        };
+        std::ops::ControlFlow::<()>::Continue(())
+    });
+    tables.into_iter().collect()
+}
+
+// Stored procedures and functions with their parameters. Bodies are only included when
+// describing a single routine.
+async fn get_routines(pool: &Pool<MySql>, database: &str, name: Option<&str>) -> Result<Vec<Value>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT specific_name AS specific_name, routine_name AS routine_name, routine_type AS routine_type,
+                dtd_identifier AS dtd_identifier, routine_definition AS routine_definition,
+                is_deterministic AS is_deterministic, sql_data_access AS sql_data_access,
+                security_type AS security_type, definer AS definer, routine_comment AS routine_comment
+         FROM information_schema.routines
+         WHERE routine_schema = ? AND (? IS NULL OR routine_name = ?)
+         ORDER BY routine_type, routine_name",
+    )
+    .bind(database)
+    .bind(name)
+    .bind(name)
+    .fetch_all(pool)
+    .await?;
+
+    // Ordinal 0 is the return value of a function, which is reported on the routine itself
+    let parameter_rows = sqlx::query(
+        "SELECT specific_name AS specific_name, parameter_mode AS parameter_mode,
+                parameter_name AS parameter_name, dtd_identifier AS dtd_identifier
+         FROM information_schema.parameters
+         WHERE specific_schema = ? AND ordinal_position > 0 AND (? IS NULL OR specific_name = ?)
+         ORDER BY specific_name, ordinal_position",
+    )
+    .bind(database)
+    .bind(name)
+    .bind(name)
+    .fetch_all(pool)
+    .await?;
+
+    let mut parameters: HashMap<String, Vec<Value>> = HashMap::new();
+    for row in parameter_rows {
+        parameters
+            .entry(row.try_get::<String, _>("specific_name").unwrap_or_default())
+            .or_default()
+            .push(json!({
+                "name": row.try_get::<Option<String>, _>("parameter_name").unwrap_or_default(),
+                "mode": row.try_get::<Option<String>, _>("parameter_mode").unwrap_or_default(),
+                "type": row.try_get::<String, _>("dtd_identifier").unwrap_or_default(),
+            }));
+    }
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let specific_name = row.try_get::<String, _>("specific_name").unwrap_or_default();
+            let routine_type = row.try_get::<String, _>("routine_type").unwrap_or_default();
+            let mut routine = json!({
+                "name": row.try_get::<String, _>("routine_name").unwrap_or_default(),
+                "type": routine_type,
+                "parameters": parameters.remove(&specific_name).unwrap_or_default(),
+                "deterministic": row.try_get::<String, _>("is_deterministic").unwrap_or_default() == "YES",
+                "sql_data_access": row.try_get::<String, _>("sql_data_access").unwrap_or_default(),
+                "security_type": row.try_get::<String, _>("security_type").unwrap_or_default(),
+                "definer": row.try_get::<String, _>("definer").unwrap_or_default(),
+                "comment": row.try_get::<String, _>("routine_comment").unwrap_or_default(),
+            });
+            if routine_type == "FUNCTION" {
+                routine["returns"] = json!(row.try_get::<Option<String>, _>("dtd_identifier").unwrap_or_default());
             }
+            if name.is_some() {
+                // NULL when the user lacks privileges to see the body
+                routine["body"] = json!(row.try_get::<Option<String>, _>("routine_definition").unwrap_or_default());
+            }
+            routine
+        })
+        .collect())
+}
+
+// Triggers with their table, timing and event. Statements are only included when describing
+// a single trigger.
+async fn get_triggers(pool: &Pool<MySql>, database: &str, name: Option<&str>) -> Result<Vec<Value>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT trigger_name AS trigger_name, event_manipulation AS event_manipulation,
+                event_object_table AS event_object_table, action_timing AS action_timing,
+                CAST(action_order AS UNSIGNED) AS action_order, action_statement AS action_statement,
+                definer AS definer
+         FROM information_schema.triggers
+         WHERE trigger_schema = ? AND (? IS NULL OR trigger_name = ?)
+         ORDER BY event_object_table, action_timing, event_manipulation, action_order",
+    )
+    .bind(database)
+    .bind(name)
+    .bind(name)
+    .fetch_all(pool)
+    .await?;
+
+    let mut triggers = Vec::new();
+    for row in rows {
+        let mut trigger = json!({
+            "name": row.try_get::<String, _>("trigger_name").unwrap_or_default(),
+            "table": row.try_get::<String, _>("event_object_table").unwrap_or_default(),
+            "timing": row.try_get::<String, _>("action_timing").unwrap_or_default(),
+            "event": row.try_get::<String, _>("event_manipulation").unwrap_or_default(),
+            // BIGINT on MySQL 5.7 and MariaDB, INT UNSIGNED on MySQL 8, hence the cast
+            "order": row.try_get::<u64, _>("action_order")?,
+            "definer": row.try_get::<String, _>("definer").unwrap_or_default(),
+        });
+        if name.is_some() {
+            trigger["statement"] = json!(row.try_get::<String, _>("action_statement")?);
         }
+        triggers.push(trigger);
     }
-    
+    Ok(triggers)
+}
+
+// Scheduled events with their schedule and status. Bodies are only included when describing
+// a single event.
+async fn get_events(pool: &Pool<MySql>, database: &str, name: Option<&str>) -> Result<Vec<Value>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT event_name AS event_name, event_type AS event_type,
+                CAST(execute_at AS CHAR) AS execute_at, interval_value AS interval_value,
+                interval_field AS interval_field, CAST(starts AS CHAR) AS starts, CAST(ends AS CHAR) AS ends,
+                status AS status, on_completion AS on_completion, CAST(last_executed AS CHAR) AS last_executed,
+                event_definition AS event_definition, event_comment AS event_comment, definer AS definer
+         FROM information_schema.events
+         WHERE event_schema = ? AND (? IS NULL OR event_name = ?)
+         ORDER BY event_name",
+    )
+    .bind(database)
+    .bind(name)
+    .bind(name)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let mut event = json!({
+                "name": row.try_get::<String, _>("event_name").unwrap_or_default(),
+                "type": row.try_get::<String, _>("event_type").unwrap_or_default(),
+                "execute_at": row.try_get::<Option<String>, _>("execute_at").unwrap_or_default(),
+                "interval_value": row.try_get::<Option<String>, _>("interval_value").unwrap_or_default(),
+                "interval_field": row.try_get::<Option<String>, _>("interval_field").unwrap_or_default(),
+                "starts": row.try_get::<Option<String>, _>("starts").unwrap_or_default(),
+                "ends": row.try_get::<Option<String>, _>("ends").unwrap_or_default(),
+                "status": row.try_get::<String, _>("status").unwrap_or_default(),
+                "on_completion": row.try_get::<String, _>("on_completion").unwrap_or_default(),
+                "last_executed": row.try_get::<Option<String>, _>("last_executed").unwrap_or_default(),
+                "comment": row.try_get::<String, _>("event_comment").unwrap_or_default(),
+                "definer": row.try_get::<String, _>("definer").unwrap_or_default(),
+            });
+            if name.is_some() {
+                event["body"] = json!(row.try_get::<String, _>("event_definition").unwrap_or_default());
+            }
+            event
+        })
+        .collect())
+}
+
+async fn get_table_names(pool: &Pool<MySql>, database: &str) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT table_name AS table_name FROM information_schema.tables
+         WHERE table_schema = ? AND table_type = 'BASE TABLE'
+         ORDER BY table_name",
+    )
+    .bind(database)
+    .fetch_all(pool)
+    .await
+}
+
+// `get_table_names` through the session's schema cache
+async fn cached_table_names(pool: &Pool<MySql>, database: &str, ctx: &RequestContext) -> Result<Vec<String>, sqlx::Error> {
+    if let Some(tables) = ctx.schema_cache.table_names(database) {
+        return Ok(tables);
+    }
+    let tables = get_table_names(pool, database).await?;
+    store_table_names(database, &tables, ctx);
+    Ok(tables)
+}
+
+// Cache freshly read table names, telling the client if they changed
+fn store_table_names(database: &str, tables: &[String], ctx: &RequestContext) {
+    if ctx.schema_cache.store_table_names(database, tables) {
+        ctx.notify_resource_list_changed();
+    }
+}
+
+// `get_table_schema` through the session's schema cache
+async fn cached_table_schema(
+    pool: &Pool<MySql>,
+    database: &str,
+    table_name: &str,
+    ctx: &RequestContext,
+) -> Result<Value, SchemaError> {
+    if let Some(schema) = ctx.schema_cache.schema(database, table_name) {
+        return Ok(schema);
+    }
+    let schema = get_table_schema(pool, database, table_name).await?;
+    ctx.schema_cache.store_schema(database, table_name, &schema);
+    Ok(schema)
+}
+
+// Every base table schema of `database`, from the cache if it holds all of them and otherwise
+// read in one pass
+async fn get_all_table_schemas(
+    pool: &Pool<MySql>,
+    database: &str,
+    ctx: &RequestContext,
+) -> Result<Vec<Value>, SchemaError> {
+    if let Some(tables) = ctx.schema_cache.table_names(database) {
+        let cached: Option<Vec<Value>> = tables
+            .iter()
+            .map(|table_name| ctx.schema_cache.schema(database, table_name))
+            .collect();
+        if let Some(schemas) = cached {
+            return Ok(schemas);
+        }
+    }
+
+    let schemas = read_table_schemas(pool, database, None, Some(ctx)).await?;
+    let tables: Vec<String> = schemas
+        .iter()
+        .map(|schema| schema["table_name"].as_str().unwrap_or_default().to_string())
+        .collect();
+    for (table_name, schema) in tables.iter().zip(&schemas) {
+        ctx.schema_cache.store_schema(database, table_name, schema);
+    }
+    store_table_names(database, &tables, ctx);
     Ok(schemas)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursors_round_trip() {
+        let cursor = QueryCursor {
+            query: "SELECT * FROM orders WHERE note = 'a/b+c'".to_string(),
+            offset: 500,
+            database: Some("billing".to_string()),
+        };
+        let encoded = encode_cursor(&cursor);
+        assert!(encoded.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'), "{encoded}");
+        let decoded = decode_cursor(&encoded).unwrap();
+        assert_eq!((decoded.query.as_str(), decoded.offset), (cursor.query.as_str(), 500));
+        assert_eq!(decoded.database.as_deref(), Some("billing"));
+        // A cursor without a database runs in the default one
+        let default = URL_SAFE_NO_PAD.encode(r#"{"query":"SELECT 1","offset":10}"#);
+        assert_eq!(decode_cursor(&default).map(|cursor| cursor.database), Some(None));
+    }
+
+    #[test]
+    fn invalid_cursors_are_rejected() {
+        assert!(decode_cursor("not a cursor!").is_none());
+        assert!(decode_cursor(&URL_SAFE_NO_PAD.encode("{\"query\": 1}")).is_none());
+        assert!(decode_cursor("").is_none());
+    }
+
+    #[test]
+    fn enum_values_unquotes_members() {
+        assert_eq!(enum_values("enum('active','inactive')"), Some(vec!["active".to_string(), "inactive".to_string()]));
+        assert_eq!(enum_values("set('a','b','c')").map(|values| values.len()), Some(3));
+        assert_eq!(enum_values("enum('it''s','a,b','back\\\\slash')"), Some(vec![
+            "it's".to_string(),
+            "a,b".to_string(),
+            "back\\slash".to_string(),
+        ]));
+        assert_eq!(enum_values("enum('')"), Some(vec![String::new()]));
+    }
+
+    #[test]
+    fn table_schema_uris_round_trip() {
+        assert_eq!(table_schema_uri("shop", "orders"), "mysql://shop/orders/schema");
+        for (database, table_name) in [("shop", "orders"), ("my db", "a/b?c#d"), ("données", "100%_ready")] {
+            let uri = table_schema_uri(database, table_name);
+            assert_eq!(parse_table_schema_uri(&uri), Some((database.to_string(), table_name.to_string())), "{uri}");
+        }
+        assert_eq!(table_schema_uri("shop", "a/b"), "mysql://shop/a%2Fb/schema");
+    }
+
+    #[test]
+    fn parse_table_schema_uri_rejects_malformed_uris() {
+        assert_eq!(parse_table_schema_uri("mysql://shop/schema"), None);
+        assert_eq!(parse_table_schema_uri("mysql:///orders/schema"), None);
+        assert_eq!(parse_table_schema_uri("mysql://shop/bad%2/schema"), None);
+        assert_eq!(parse_table_schema_uri("mysql://shop/bad%zz/schema"), None);
+        assert_eq!(parse_table_schema_uri("mysql://shop/%FF/schema"), None);
+        assert_eq!(parse_table_schema_uri("postgres://shop/orders/schema"), None);
+    }
+
+    #[test]
+    fn enum_values_ignores_other_types() {
+        assert_eq!(enum_values("varchar(255)"), None);
+        assert_eq!(enum_values("int unsigned"), None);
+    }
+
+    #[test]
+    fn compact_schema_marks_keys_and_nullability() {
+        let schema = json!({
+            "table_name": "orders",
+            "comment": "Customer orders",
+            "columns": [
+                { "name": "id", "key": "PRI", "column_type": "int", "nullable": false },
+                { "name": "number", "key": "UNI", "column_type": "varchar(20)", "nullable": false },
+                { "name": "user_id", "key": "MUL", "column_type": "int", "nullable": false },
+                { "name": "invoice_id", "key": "MUL", "column_type": "bigint", "nullable": true },
+                { "name": "note", "key": "", "column_type": "text", "nullable": true },
+            ],
+            "foreign_keys": [
+                {
+                    "schema": "shop",
+                    "columns": ["user_id"],
+                    "referenced_schema": "shop",
+                    "referenced_table": "users",
+                    "referenced_columns": ["id"],
+                },
+                {
+                    "schema": "shop",
+                    "columns": ["invoice_id"],
+                    "referenced_schema": "billing",
+                    "referenced_table": "invoices",
+                    "referenced_columns": ["id"],
+                },
+            ],
+        });
+        assert_eq!(
+            compact_schema(&schema),
+            "orders(id PK int, number UQ varchar(20), user_id FK→users.id int, \
+             invoice_id FK→billing.invoices.id bigint NULL, note text NULL) -- Customer orders"
+        );
+    }
+
+    #[test]
+    fn compact_schema_maps_composite_foreign_keys_by_position() {
+        let schema = json!({
+            "table_name": "order_items",
+            "comment": "",
+            "columns": [
+                { "name": "order_id", "key": "PRI", "column_type": "int", "nullable": false },
+                { "name": "line", "key": "PRI", "column_type": "int", "nullable": false },
+            ],
+            "foreign_keys": [{
+                "schema": "shop",
+                "columns": ["order_id", "line"],
+                "referenced_schema": "shop",
+                "referenced_table": "order_lines",
+                "referenced_columns": ["order_ref", "line_no"],
+            }],
+        });
+        assert_eq!(
+            compact_schema(&schema),
+            "order_items(order_id PK FK→order_lines.order_ref int, line PK FK→order_lines.line_no int)"
+        );
+    }
+
+    #[test]
+    fn view_tables_qualifies_every_relation() {
+        assert_eq!(
+            view_tables(
+                "select `o`.`id` AS `id`, `u`.`email` AS `email` from (`shop`.`orders` `o` join `crm`.`users` `u` on((`u`.`id` = `o`.`user_id`)))",
+                "shop"
+            ),
+            ["crm.users", "shop.orders"]
+        );
+        assert_eq!(
+            view_tables("select id from orders where id in (select order_id from order_items)", "shop"),
+            ["shop.order_items", "shop.orders"]
+        );
+    }
+
+    #[test]
+    fn view_tables_ignores_unparsable_definitions() {
+        assert!(view_tables("not a query", "shop").is_empty());
+    }
+
+    fn statistics_row(index: &str, column: &str, cardinality: i64, extra: Value) -> Map<String, Value> {
+        let Value::Object(mut row) = json!({
+            "INDEX_NAME": index,
+            "COLUMN_NAME": column,
+            "NON_UNIQUE": if index == "PRIMARY" { 0 } else { 1 },
+            "INDEX_TYPE": "BTREE",
+            "COLLATION": "A",
+            "CARDINALITY": cardinality,
+        }) else {
+            unreachable!()
+        };
+        row.extend(extra.as_object().cloned().unwrap_or_default());
+        row
+    }
+
+    #[test]
+    fn group_index_rows_collects_composite_indexes() {
+        let rows = [
+            statistics_row("PRIMARY", "id", 1000, json!({})),
+            statistics_row("idx_customer_date", "customer_id", 50, json!({ "IS_VISIBLE": "NO" })),
+            statistics_row("idx_customer_date", "created_at", 900, json!({ "IS_VISIBLE": "NO", "COLLATION": "D" })),
+            statistics_row("idx_note", "note", 10, json!({ "SUB_PART": 20, "IGNORED": "YES" })),
+        ];
+        let indexes = group_index_rows(&rows);
+        assert_eq!(indexes.len(), 3);
+
+        assert_eq!(indexes[0]["primary"], true);
+        assert_eq!(indexes[0]["unique"], true);
+        assert_eq!(indexes[0]["visible"], true);
+
+        let composite = &indexes[1];
+        assert_eq!(composite["unique"], false);
+        assert_eq!(composite["cardinality"], 900);
+        assert_eq!(composite["visible"], false);
+        let columns: Vec<_> = composite["columns"].as_array().unwrap().iter().map(|c| (&c["name"], &c["order"])).collect();
+        assert_eq!(columns, [(&json!("customer_id"), &json!("ASC")), (&json!("created_at"), &json!("DESC"))]);
+
+        assert_eq!(indexes[2]["columns"][0]["sub_part"], 20);
+        assert_eq!(indexes[2]["visible"], false);
+    }
+
+    #[test]
+    fn query_databases_must_be_allowed() {
+        let args = |allowed: &str| {
+            Args::parse_from(["mcp-server-mysql", "--username", "app", "--database", "shop", "--allowed-databases", allowed])
+        };
+        let check = |args: &Args, current: &str, query: &str| check_query_databases(args, "shop", current, query);
+
+        let restricted = args("crm");
+        assert!(check(&restricted, "shop", "SELECT * FROM orders").is_ok());
+        assert!(check(&restricted, "shop", "SELECT * FROM crm.users").is_ok());
+        assert!(check(&restricted, "crm", "SELECT * FROM shop.orders JOIN users").is_ok());
+        assert!(check(&restricted, "shop", "SELECT * FROM INFORMATION_SCHEMA.TABLES").is_ok());
+        assert!(check(&restricted, "shop", "SELECT * FROM billing.invoices").unwrap_err().contains("'billing'"));
+        assert!(check(&restricted, "shop", "SHOW TABLES FROM billing").is_err());
+
+        assert!(check(&args("*"), "shop", "SELECT * FROM billing.invoices").is_ok());
+    }
+
+    #[test]
+    fn max_rows_must_be_positive() {
+        let parse = |max_rows: &str| {
+            Args::try_parse_from(["mcp-server-mysql", "--username", "app", "--database", "shop", "--max-rows", max_rows])
+        };
+        assert_eq!(parse("1").map(|args| args.max_rows).ok(), Some(1));
+        assert!(parse("0").is_err());
+    }
+}
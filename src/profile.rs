@@ -0,0 +1,361 @@
+// Sample rows and per-column value statistics of a table.
+//
+// A schema says `status` is a `varchar(20)`, not whether it holds 'active' or '1'. Every
+// statistic here is computed over at most `scan_rows` rows of the table, so profiling a huge
+// table costs no more than a small one, and values are decoded the same way as query results.
+
+use std::time::Duration;
+
+use serde_json::{json, Map, Value};
+use sqlx::mysql::MySqlRow;
+use sqlx::pool::PoolConnection;
+use sqlx::{MySql, Pool};
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    abort_statement, cached_table_schema, decode, interruptible, quote_identifier, run_detached, RequestContext, SchemaError,
+};
+
+// Columns with at most this many distinct values get their most frequent values listed
+const LOW_CARDINALITY: u64 = 20;
+// Longer strings are cut short in samples and statistics, so one text column can't flood the response
+const MAX_VALUE_CHARS: usize = 100;
+// Types that have no useful ordering or distinct values; only their null fraction is reported
+const UNCOMPARABLE_TYPES: &[&str] = &[
+    "tinyblob",
+    "blob",
+    "mediumblob",
+    "longblob",
+    "json",
+    "geometry",
+    "point",
+    "linestring",
+    "polygon",
+    "multipoint",
+    "multilinestring",
+    "multipolygon",
+    "geometrycollection",
+];
+
+#[derive(Clone)]
+pub struct ProfileOptions {
+    pub sample_rows: usize,
+    pub top_values: usize,
+    pub scan_rows: u64,
+    pub timeout: Option<Duration>,
+}
+
+/// Profiles up to `options.scan_rows` rows of a table: a few random sample rows from them, and
+/// for each column its null fraction, distinct count, minimum and maximum, plus the most
+/// frequent values of low-cardinality columns.
+pub async fn profile_table(
+    pool: &Pool<MySql>,
+    database: &str,
+    table_name: &str,
+    options: &ProfileOptions,
+    ctx: &RequestContext,
+) -> Result<Value, SchemaError> {
+    let schema = cached_table_schema(pool, database, table_name, ctx).await?;
+    let scan = scan_table(
+        pool.clone(),
+        database.to_string(),
+        table_name.to_string(),
+        schema,
+        options.clone(),
+        ctx.clone(),
+    );
+    run_detached(scan).await
+}
+
+async fn scan_table(
+    pool: Pool<MySql>,
+    database: String,
+    table_name: String,
+    schema: Value,
+    options: ProfileOptions,
+    ctx: RequestContext,
+) -> Result<Value, SchemaError> {
+    let columns: Vec<&Value> = schema["columns"].as_array().into_iter().flatten().collect();
+    let mut scanner = Scanner::connect(&pool, options.timeout, &ctx.cancel).await?;
+
+    let table = format!("{}.{}", quote_identifier(&database), quote_identifier(&table_name));
+    let scanned = format!("(SELECT * FROM {table} LIMIT {}) AS scanned", options.scan_rows);
+
+    // One pass for every column's counts and bounds, aliased by position
+    let mut aggregates = vec!["COUNT(*) AS row_count".to_string()];
+    for (i, column) in columns.iter().enumerate() {
+        let name = quote_identifier(column["name"].as_str().unwrap_or_default());
+        aggregates.push(format!("COUNT({name}) AS non_null_{i}"));
+        if is_comparable(column) {
+            aggregates.push(format!("COUNT(DISTINCT {name}) AS distinct_{i}"));
+            aggregates.push(format!("MIN({name}) AS min_{i}"));
+            aggregates.push(format!("MAX({name}) AS max_{i}"));
+        }
+    }
+    ctx.report_progress(0, columns.len() + 1, &format!("Scanning up to {} rows", options.scan_rows));
+    let rows = scanner.fetch_all(&format!("SELECT {} FROM {scanned}", aggregates.join(", "))).await?;
+    let stats = rows.first().map(decode::row_to_json).unwrap_or_default();
+    let rows_scanned = stats.get("row_count").and_then(Value::as_u64).unwrap_or_default();
+    // Otherwise the statistics only describe the first rows the server returned. A table with
+    // exactly `scan_rows` rows fills the budget too, so count one row past it to tell them apart.
+    let complete = if rows_scanned < options.scan_rows {
+        true
+    } else {
+        let rows = scanner
+            .fetch_all(&format!(
+                "SELECT COUNT(*) AS row_count FROM (SELECT 1 FROM {table} LIMIT {}) AS counted",
+                options.scan_rows.saturating_add(1)
+            ))
+            .await?;
+        let row_count = rows.first().map(decode::row_to_json).and_then(|row| row.get("row_count").and_then(Value::as_u64));
+        row_count.is_some_and(|row_count| row_count <= options.scan_rows)
+    };
+
+    let mut profiles = Vec::new();
+    for (i, column) in columns.iter().enumerate() {
+        if ctx.is_cancelled() {
+            break;
+        }
+        let name = column["name"].as_str().unwrap_or_default();
+        let non_null = stats.get(&format!("non_null_{i}")).and_then(Value::as_u64).unwrap_or_default();
+        let distinct = stats.get(&format!("distinct_{i}")).and_then(Value::as_u64);
+        let mut profile = json!({
+            "name": name,
+            "column_type": column["column_type"],
+            "null_fraction": (rows_scanned > 0).then(|| round((rows_scanned - non_null) as f64 / rows_scanned as f64)),
+            "distinct": distinct,
+            "estimated_distinct": index_cardinality(&schema, name),
+            "min": shorten(stats.get(&format!("min_{i}")).cloned().unwrap_or(Value::Null)),
+            "max": shorten(stats.get(&format!("max_{i}")).cloned().unwrap_or(Value::Null)),
+        });
+
+        if options.top_values > 0 && distinct.is_some_and(|distinct| distinct > 0 && distinct <= LOW_CARDINALITY) {
+            ctx.report_progress(i + 1, columns.len() + 1, &format!("Counting values of '{name}'"));
+            let quoted = quote_identifier(name);
+            let rows = scanner
+                .fetch_all(&format!(
+                    "SELECT {quoted} AS value, COUNT(*) AS count FROM {scanned}
+                     WHERE {quoted} IS NOT NULL
+                     GROUP BY {quoted}
+                     ORDER BY count DESC, value
+                     LIMIT {}",
+                    options.top_values
+                ))
+                .await?;
+            let top_values: Vec<Value> = rows
+                .iter()
+                .map(|row| {
+                    let mut row = decode::row_to_json(row);
+                    json!({
+                        "value": shorten(row.remove("value").unwrap_or(Value::Null)),
+                        "count": row.remove("count").unwrap_or(Value::Null),
+                    })
+                })
+                .collect();
+            profile["top_values"] = json!(top_values);
+        }
+        profiles.push(profile);
+    }
+
+    let sample_rows: Vec<Value> = if options.sample_rows > 0 {
+        scanner
+            .fetch_all(&format!("SELECT * FROM {scanned} ORDER BY RAND() LIMIT {}", options.sample_rows))
+            .await?
+            .iter()
+            .map(|row| {
+                let row: Map<String, Value> = decode::row_to_json(row)
+                    .into_iter()
+                    .map(|(column, value)| (column, shorten(value)))
+                    .collect();
+                Value::Object(row)
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    Ok(json!({
+        "database": database,
+        "table_name": table_name,
+        "estimated_rows": schema["statistics"]["estimated_rows"],
+        "rows_scanned": rows_scanned,
+        "complete": complete,
+        "columns": profiles,
+        "sample_rows": sample_rows,
+    }))
+}
+
+// Runs the statements of one profile on a dedicated connection against a single deadline. A
+// statement still running at the deadline or when the request is cancelled is killed, as in the
+// query tool, so the timeout holds on MariaDB too.
+struct Scanner<'a> {
+    pool: &'a Pool<MySql>,
+    conn: PoolConnection<MySql>,
+    connection_id: u64,
+    started: Instant,
+    timeout: Option<Duration>,
+    cancel: &'a CancellationToken,
+}
+
+impl<'a> Scanner<'a> {
+    async fn connect(
+        pool: &'a Pool<MySql>,
+        timeout: Option<Duration>,
+        cancel: &'a CancellationToken,
+    ) -> Result<Self, SchemaError> {
+        let started = Instant::now();
+        let mut conn = pool.acquire().await?;
+        let connection_id = sqlx::query_scalar::<_, u64>("SELECT CONNECTION_ID()").fetch_one(&mut *conn).await?;
+        Ok(Scanner {
+            pool,
+            conn,
+            connection_id,
+            started,
+            timeout,
+            cancel,
+        })
+    }
+
+    async fn fetch_all(&mut self, sql: &str) -> Result<Vec<MySqlRow>, SchemaError> {
+        let statement = sqlx::query(sql).fetch_all(&mut *self.conn);
+        match interruptible(statement, self.started, self.timeout, self.cancel).await {
+            Ok(rows) => Ok(rows?),
+            Err(interrupted) => {
+                abort_statement(self.pool, &mut self.conn, self.connection_id).await;
+                Err(interrupted.into())
+            }
+        }
+    }
+}
+
+/// Summary of a profile, one line per column.
+pub fn describe(profile: &Value) -> String {
+    let table_name = profile["table_name"].as_str().unwrap_or_default();
+    let rows_scanned = profile["rows_scanned"].as_u64().unwrap_or_default();
+    let mut lines = vec![match profile["estimated_rows"].as_u64() {
+        _ if profile["complete"] == true => format!("{table_name}: all {rows_scanned} rows scanned"),
+        Some(estimated_rows) => format!("{table_name}: first {rows_scanned} of about {estimated_rows} rows scanned"),
+        None => format!("{table_name}: first {rows_scanned} rows scanned"),
+    }];
+
+    for column in profile["columns"].as_array().into_iter().flatten() {
+        let mut details = Vec::new();
+        if let Some(fraction) = column["null_fraction"].as_f64() {
+            details.push(format!("{:.1}% null", fraction * 100.0));
+        }
+        if let Some(distinct) = column["distinct"].as_u64() {
+            details.push(format!("{distinct} distinct"));
+        }
+        if !column["min"].is_null() {
+            details.push(format!("min {}, max {}", column["min"], column["max"]));
+        }
+        if let Some(top_values) = column["top_values"].as_array() {
+            let values: Vec<String> = top_values
+                .iter()
+                .map(|top| format!("{} ×{}", top["value"], top["count"]))
+                .collect();
+            details.push(format!("top {}", values.join(", ")));
+        }
+        lines.push(format!(
+            "- {} {}: {}",
+            column["name"].as_str().unwrap_or_default(),
+            column["column_type"].as_str().unwrap_or_default(),
+            details.join(", ")
+        ));
+    }
+    lines.join("\n")
+}
+
+fn is_comparable(column: &Value) -> bool {
+    column["type"]
+        .as_str()
+        .is_some_and(|data_type| !UNCOMPARABLE_TYPES.contains(&data_type.to_lowercase().as_str()))
+}
+
+// Cardinality of an index on just this column, the server's estimate over the whole table
+fn index_cardinality(schema: &Value, column: &str) -> Value {
+    schema["indexes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|index| index["columns"].as_array().is_some_and(|columns| columns.len() == 1 && columns[0]["name"] == column))
+        .map(|index| index["cardinality"].clone())
+        .unwrap_or(Value::Null)
+}
+
+fn shorten(value: Value) -> Value {
+    match value {
+        Value::String(text) if text.chars().count() > MAX_VALUE_CHARS => {
+            Value::String(format!("{}…", text.chars().take(MAX_VALUE_CHARS).collect::<String>()))
+        }
+        // Binary values are decoded to `{"base64": ...}`, of which only the size is worth showing
+        Value::Object(object) => match object.get("base64").and_then(Value::as_str) {
+            Some(base64) if object.len() == 1 && base64.len() > MAX_VALUE_CHARS => {
+                let padding = base64.bytes().rev().take_while(|b| *b == b'=').count();
+                json!({ "base64_bytes": base64.len() / 4 * 3 - padding })
+            }
+            _ => Value::Object(object),
+        },
+        value => value,
+    }
+}
+
+fn round(value: f64) -> f64 {
+    (value * 10000.0).round() / 10000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+
+    #[test]
+    fn shorten_truncates_long_strings() {
+        let long = "é".repeat(MAX_VALUE_CHARS + 10);
+        let shortened = shorten(json!(long));
+        assert_eq!(shortened.as_str().unwrap().chars().count(), MAX_VALUE_CHARS + 1);
+        assert!(shortened.as_str().unwrap().ends_with('…'));
+
+        let exact = "a".repeat(MAX_VALUE_CHARS);
+        assert_eq!(shorten(json!(exact)), json!(exact));
+        assert_eq!(shorten(json!(42)), json!(42));
+    }
+
+    #[test]
+    fn shorten_replaces_long_binary_values_by_their_size() {
+        let base64 = |bytes: &[u8]| json!({ "base64": base64::engine::general_purpose::STANDARD.encode(bytes) });
+        assert_eq!(shorten(base64(&[7; 1000])), json!({ "base64_bytes": 1000 }));
+        assert_eq!(shorten(base64(&[7; 1001])), json!({ "base64_bytes": 1001 }));
+        assert_eq!(shorten(base64(&[7; 1002])), json!({ "base64_bytes": 1002 }));
+        assert_eq!(shorten(base64(b"short")), base64(b"short"));
+        assert_eq!(shorten(json!({ "a": "b".repeat(200) })), json!({ "a": "b".repeat(200) }));
+    }
+
+    #[test]
+    fn describe_summarises_each_column() {
+        let profile = json!({
+            "table_name": "orders",
+            "rows_scanned": 1000,
+            "estimated_rows": 25000,
+            "complete": false,
+            "columns": [
+                {
+                    "name": "status", "column_type": "varchar(20)", "null_fraction": 0.0, "distinct": 3,
+                    "min": "new", "max": "shipped",
+                    "top_values": [{ "value": "new", "count": 600 }, { "value": "paid", "count": 300 }]
+                },
+                { "name": "note", "column_type": "text", "null_fraction": 0.125, "distinct": 40, "min": null }
+            ]
+        });
+        assert_eq!(
+            describe(&profile),
+            "orders: first 1000 of about 25000 rows scanned\n\
+             - status varchar(20): 0.0% null, 3 distinct, min \"new\", max \"shipped\", top \"new\" ×600, \"paid\" ×300\n\
+             - note text: 12.5% null, 40 distinct"
+        );
+
+        let complete = json!({ "table_name": "tags", "rows_scanned": 7, "estimated_rows": 10, "complete": true });
+        assert_eq!(describe(&complete), "tags: all 7 rows scanned");
+    }
+}
@@ -0,0 +1,251 @@
+// Keyword search over table and column names and comments.
+//
+// Names and comments of a whole database are read in two queries and ranked here, so a
+// question like "where do we store invoice totals" finds `invoices.total_amount` without
+// sending every table schema to the model.
+
+use serde_json::{json, Value};
+use sqlx::{MySql, Pool, Row};
+
+// Weights of a term matching the different parts of a candidate
+const EXACT_NAME: u32 = 10;
+const NAME_WORD: u32 = 6;
+const NAME_PART: u32 = 4;
+const COMMENT: u32 = 2;
+const TABLE_CONTEXT: u32 = 2;
+
+struct Candidate {
+    table: String,
+    table_type: String,
+    table_comment: String,
+    // Column name, type and comment; `None` for the table itself
+    column: Option<(String, String, String)>,
+}
+
+/// Returns up to `limit` tables and columns matching `query`, best matches first.
+///
+/// `query` is split into keywords matched case-insensitively against names and comments;
+/// `*` and `%` inside a keyword match any run of characters.
+pub async fn search_schema(
+    pool: &Pool<MySql>,
+    database: &str,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<Value>, sqlx::Error> {
+    let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+
+    let tables = sqlx::query(
+        "SELECT table_name AS table_name, table_type AS table_type, table_comment AS table_comment
+         FROM information_schema.tables
+         WHERE table_schema = ?",
+    )
+    .bind(database)
+    .fetch_all(pool)
+    .await?;
+    let mut candidates: Vec<Candidate> = tables
+        .iter()
+        .map(|row| Candidate {
+            table: row.try_get::<String, _>("table_name").unwrap_or_default(),
+            table_type: row.try_get::<String, _>("table_type").unwrap_or_default(),
+            table_comment: row.try_get::<Option<String>, _>("table_comment").unwrap_or_default().unwrap_or_default(),
+            column: None,
+        })
+        .collect();
+
+    let columns = sqlx::query(
+        "SELECT c.table_name AS table_name, t.table_type AS table_type, t.table_comment AS table_comment,
+                c.column_name AS column_name, c.column_type AS column_type, c.column_comment AS column_comment
+         FROM information_schema.columns c
+         JOIN information_schema.tables t
+           ON t.table_schema = c.table_schema AND t.table_name = c.table_name
+         WHERE c.table_schema = ?",
+    )
+    .bind(database)
+    .fetch_all(pool)
+    .await?;
+    candidates.extend(columns.iter().map(|row| Candidate {
+        table: row.try_get::<String, _>("table_name").unwrap_or_default(),
+        table_type: row.try_get::<String, _>("table_type").unwrap_or_default(),
+        table_comment: row.try_get::<Option<String>, _>("table_comment").unwrap_or_default().unwrap_or_default(),
+        column: Some((
+            row.try_get::<String, _>("column_name").unwrap_or_default(),
+            row.try_get::<String, _>("column_type").unwrap_or_default(),
+            row.try_get::<String, _>("column_comment").unwrap_or_default(),
+        )),
+    }));
+
+    let mut ranked: Vec<(usize, u32, Candidate)> = candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let (matched, score) = score(&candidate, &terms);
+            (matched > 0).then_some((matched, score, candidate))
+        })
+        .collect();
+    // Candidates matching more of the keywords come first, then the strongest matches
+    ranked.sort_by(|a, b| {
+        b.0.cmp(&a.0)
+            .then(b.1.cmp(&a.1))
+            .then_with(|| a.2.table.cmp(&b.2.table))
+            .then_with(|| a.2.column.is_some().cmp(&b.2.column.is_some()))
+    });
+
+    Ok(ranked
+        .into_iter()
+        .take(limit)
+        .map(|(_, score, candidate)| match candidate.column {
+            None => json!({
+                "kind": "table",
+                "table": candidate.table,
+                "table_type": candidate.table_type,
+                "comment": candidate.table_comment,
+                "score": score,
+            }),
+            Some((column, column_type, comment)) => json!({
+                "kind": "column",
+                "table": candidate.table,
+                "column": column,
+                "column_type": column_type,
+                "comment": comment,
+                "score": score,
+            }),
+        })
+        .collect())
+}
+
+// Number of terms the candidate matches and its total score. Columns also score for terms
+// matching their table, so "invoice total" ranks `invoices.total` above `orders.total`.
+fn score(candidate: &Candidate, terms: &[String]) -> (usize, u32) {
+    let table = candidate.table.to_lowercase();
+    let table_comment = candidate.table_comment.to_lowercase();
+
+    let mut matched = 0;
+    let mut total = 0;
+    for term in terms {
+        let best = match &candidate.column {
+            None => name_score(&table, term).max(comment_score(&table_comment, term)),
+            Some((column, _, comment)) => {
+                let in_table = name_score(&table, term) > 0 || comment_score(&table_comment, term) > 0;
+                name_score(&column.to_lowercase(), term)
+                    .max(comment_score(&comment.to_lowercase(), term))
+                    .max(if in_table { TABLE_CONTEXT } else { 0 })
+            }
+        };
+        if best > 0 {
+            matched += 1;
+            total += best;
+        }
+    }
+    (matched, total)
+}
+
+fn name_score(name: &str, term: &str) -> u32 {
+    if term.contains(['*', '%']) {
+        return if glob_match(term, name) { EXACT_NAME } else { 0 };
+    }
+    let singular = stem(term);
+    if name == term || name == singular {
+        EXACT_NAME
+    } else if name.split('_').any(|word| word == term || stem(word) == singular) {
+        NAME_WORD
+    } else if name.contains(singular) {
+        NAME_PART
+    } else {
+        0
+    }
+}
+
+fn comment_score(comment: &str, term: &str) -> u32 {
+    let matches = if term.contains(['*', '%']) {
+        glob_match(&format!("*{term}*"), comment)
+    } else {
+        comment.contains(stem(term))
+    };
+    if matches {
+        COMMENT
+    } else {
+        0
+    }
+}
+
+// Drops a plural ending so "totals" finds `total` and "categories" finds `category_id`
+fn stem(word: &str) -> &str {
+    if word.len() <= 3 {
+        return word;
+    }
+    word.strip_suffix("ies")
+        .or_else(|| word.strip_suffix("ses"))
+        .or_else(|| word.strip_suffix('s'))
+        .unwrap_or(word)
+}
+
+// Matches `text` against `pattern`, where `*` and `%` match any run of characters
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split(['*', '%']);
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let last = parts.pop();
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    match last {
+        Some(last) => rest.len() >= last.len() && rest.ends_with(last),
+        None => rest.is_empty(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(table: &str, column: &str, comment: &str) -> Candidate {
+        Candidate {
+            table: table.to_string(),
+            table_type: "BASE TABLE".to_string(),
+            table_comment: String::new(),
+            column: Some((column.to_string(), "int".to_string(), comment.to_string())),
+        }
+    }
+
+    #[test]
+    fn glob_match_wildcards() {
+        assert!(glob_match("invoice*", "invoice_items"));
+        assert!(glob_match("%_id", "customer_id"));
+        assert!(glob_match("*total*", "grand_total_amount"));
+        assert!(glob_match("a*b*c", "abc"));
+        assert!(glob_match("exact", "exact"));
+        assert!(!glob_match("exact", "exactly"));
+        assert!(!glob_match("*_id", "identity"));
+        assert!(!glob_match("ab*ba", "aba"));
+    }
+
+    #[test]
+    fn stem_drops_plural_endings() {
+        assert_eq!(stem("totals"), "total");
+        assert_eq!(stem("categories"), "categor");
+        assert_eq!(stem("addresses"), "addres");
+        assert_eq!(stem("gas"), "gas");
+    }
+
+    #[test]
+    fn table_context_ranks_columns() {
+        let terms = vec!["invoice".to_string(), "total".to_string()];
+        let (invoice_matched, invoice_score) = score(&column("invoices", "total", ""), &terms);
+        let (order_matched, order_score) = score(&column("orders", "total", ""), &terms);
+        assert_eq!((invoice_matched, order_matched), (2, 1));
+        assert!(invoice_score > order_score);
+    }
+
+    #[test]
+    fn comments_match_with_lower_weight() {
+        let terms = vec!["amount".to_string()];
+        assert_eq!(score(&column("orders", "amount", ""), &terms), (1, EXACT_NAME));
+        assert_eq!(score(&column("orders", "total", "Amount in cents"), &terms), (1, COMMENT));
+        assert_eq!(score(&column("orders", "total", ""), &terms), (0, 0));
+    }
+}
@@ -0,0 +1,320 @@
+// Versioned schema snapshots and the differences between them.
+//
+// A snapshot is the `get_table_schema` output of every table plus a small header, so it can
+// be committed next to migrations and compared later without a database. Only structural
+// properties are compared: statistics such as row estimates and cardinality always drift.
+
+use std::collections::BTreeMap;
+use std::path::{Component, Path};
+
+use serde_json::{json, Value};
+use sqlx::types::chrono::Utc;
+use sqlx::{MySql, Pool};
+
+use crate::{read_table_schemas, RequestContext, SchemaError};
+
+pub const FORMAT: &str = "mcp-server-mysql/schema-snapshot";
+pub const VERSION: u64 = 1;
+
+// Properties compared for each kind of object, everything else is informational
+const TABLE_PROPERTIES: &[&str] = &["engine", "collation", "comment"];
+const COLUMN_PROPERTIES: &[&str] = &[
+    "column_type",
+    "nullable",
+    "default",
+    "extra",
+    "comment",
+    "character_set",
+    "collation",
+    "generation_expression",
+];
+const INDEX_PROPERTIES: &[&str] = &["columns", "primary", "unique", "type", "visible"];
+const FOREIGN_KEY_PROPERTIES: &[&str] = &[
+    "columns",
+    "referenced_schema",
+    "referenced_table",
+    "referenced_columns",
+    "on_update",
+    "on_delete",
+];
+const CHECK_PROPERTIES: &[&str] = &["clause"];
+
+/// Reads every table of `database` into a snapshot.
+pub async fn take(pool: &Pool<MySql>, database: &str, ctx: &RequestContext) -> Result<Value, SchemaError> {
+    let server_version: String = sqlx::query_scalar("SELECT VERSION()").fetch_one(pool).await?;
+    // Snapshots are compared with each other later, so read the live schema rather than the cache
+    let tables = read_table_schemas(pool, database, None, Some(ctx)).await?;
+    Ok(json!({
+        "format": FORMAT,
+        "version": VERSION,
+        "database": database,
+        "server_version": server_version,
+        "created_at": Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        "tables": tables,
+    }))
+}
+
+/// Accepts a snapshot given inline or as the name of a file in `directory`. Without a
+/// directory only inline snapshots are accepted, so callers can't read arbitrary files.
+pub fn load(snapshot: &Value, directory: Option<&Path>) -> Result<Value, String> {
+    let snapshot = match snapshot {
+        Value::String(name) => {
+            let path = snapshot_path(name, directory)?;
+            // Don't pass on the I/O error, it would tell what exists on the server
+            let text = std::fs::read_to_string(path).map_err(|_| format!("Could not read snapshot '{name}'"))?;
+            serde_json::from_str(&text).map_err(|e| format!("Snapshot '{name}' is not valid JSON: {e}"))?
+        }
+        snapshot => snapshot.clone(),
+    };
+
+    if snapshot["format"] != FORMAT {
+        return Err("Not a schema snapshot, take one with the schema_snapshot tool".to_string());
+    }
+    match snapshot["version"].as_u64() {
+        Some(version) if version <= VERSION => Ok(snapshot),
+        Some(version) => Err(format!("Snapshot version {version} is newer than this server supports ({VERSION})")),
+        None => Err("Snapshot has no version".to_string()),
+    }
+}
+
+// Path of a snapshot file named relative to `directory`, which it must not leave
+fn snapshot_path(name: &str, directory: Option<&Path>) -> Result<std::path::PathBuf, String> {
+    let Some(directory) = directory else {
+        return Err("Snapshot files are disabled, pass the snapshot inline or start the server with --snapshot-dir".to_string());
+    };
+    let relative = Path::new(name);
+    if name.is_empty() || !relative.components().all(|component| matches!(component, Component::Normal(_))) {
+        return Err(format!("Snapshot '{name}' must be a file name relative to the snapshot directory"));
+    }
+    // Symbolic links could still point outside the directory
+    let path = directory.join(relative);
+    match (path.canonicalize(), directory.canonicalize()) {
+        (Ok(path), Ok(directory)) if path.starts_with(&directory) => Ok(path),
+        _ => Err(format!("Could not read snapshot '{name}'")),
+    }
+}
+
+/// Lists what changed from the `from` snapshot to the `to` snapshot, one entry per added,
+/// removed or changed table, column, index, foreign key or check constraint.
+pub fn diff(from: &Value, to: &Value) -> Vec<Value> {
+    let mut changes = Vec::new();
+    let from_tables = by_name(&from["tables"], "table_name");
+    let to_tables = by_name(&to["tables"], "table_name");
+
+    for (name, old) in &from_tables {
+        if !to_tables.contains_key(name) {
+            changes.push(change("table", "removed", name, None, Some(old), None));
+        }
+    }
+    for (name, new) in &to_tables {
+        let Some(old) = from_tables.get(name) else {
+            changes.push(change("table", "added", name, None, None, Some(new)));
+            continue;
+        };
+        if let Some((before, after)) = changed_properties(old, new, TABLE_PROPERTIES) {
+            changes.push(change("table", "changed", name, None, Some(&before), Some(&after)));
+        }
+        for (kind, field, properties) in [
+            ("column", "columns", COLUMN_PROPERTIES),
+            ("index", "indexes", INDEX_PROPERTIES),
+            ("foreign_key", "foreign_keys", FOREIGN_KEY_PROPERTIES),
+            ("check_constraint", "check_constraints", CHECK_PROPERTIES),
+        ] {
+            diff_children(&mut changes, kind, name, &old[field], &new[field], properties);
+        }
+    }
+    changes
+}
+
+/// One line per change, e.g. `~ column users.email: column_type varchar(100) -> varchar(255)`.
+pub fn describe(change: &Value) -> String {
+    let marker = match change["change"].as_str() {
+        Some("added") => "+",
+        Some("removed") => "-",
+        _ => "~",
+    };
+    let mut line = format!(
+        "{marker} {} {}",
+        change["kind"].as_str().unwrap_or_default(),
+        change["table"].as_str().unwrap_or_default()
+    );
+    if let Some(name) = change["name"].as_str() {
+        line.push_str(&format!(".{name}"));
+    }
+    if change["change"] == "changed" {
+        let details: Vec<String> = change["from"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .map(|(property, before)| format!("{property} {before} -> {}", change["to"][property]))
+            .collect();
+        line.push_str(&format!(": {}", details.join(", ")));
+    }
+    line
+}
+
+fn diff_children(changes: &mut Vec<Value>, kind: &str, table: &str, old: &Value, new: &Value, properties: &[&str]) {
+    let old = by_name(old, "name");
+    let new = by_name(new, "name");
+    for (name, before) in &old {
+        if !new.contains_key(name) {
+            changes.push(change(kind, "removed", table, Some(name), Some(before), None));
+        }
+    }
+    for (name, after) in &new {
+        match old.get(name) {
+            None => changes.push(change(kind, "added", table, Some(name), None, Some(after))),
+            Some(before) => {
+                if let Some((before, after)) = changed_properties(before, after, properties) {
+                    changes.push(change(kind, "changed", table, Some(name), Some(&before), Some(&after)));
+                }
+            }
+        }
+    }
+}
+
+// The compared properties that differ, as `{property: value}` objects for each side
+fn changed_properties(old: &Value, new: &Value, properties: &[&str]) -> Option<(Value, Value)> {
+    let mut before = serde_json::Map::new();
+    let mut after = serde_json::Map::new();
+    for property in properties {
+        let (old_value, new_value) = (property_value(old, property), property_value(new, property));
+        if old_value != new_value {
+            before.insert(property.to_string(), old_value);
+            after.insert(property.to_string(), new_value);
+        }
+    }
+    (!before.is_empty()).then_some((Value::Object(before), Value::Object(after)))
+}
+
+// Foreign keys within one database name it, which would make every key differ between
+// e.g. `app_dev` and `app`, so compare those as null
+fn property_value(item: &Value, property: &str) -> Value {
+    if property == "referenced_schema" && item["referenced_schema"] == item["schema"] {
+        return Value::Null;
+    }
+    item[property].clone()
+}
+
+fn by_name<'a>(items: &'a Value, key: &str) -> BTreeMap<String, &'a Value> {
+    items
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|item| (item[key].as_str().unwrap_or_default().to_string(), item))
+        .collect()
+}
+
+fn change(kind: &str, change: &str, table: &str, name: Option<&str>, from: Option<&Value>, to: Option<&Value>) -> Value {
+    json!({
+        "kind": kind,
+        "change": change,
+        "table": table,
+        "name": name,
+        "from": from,
+        "to": to,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(tables: Value) -> Value {
+        json!({ "format": FORMAT, "version": VERSION, "database": "app", "tables": tables })
+    }
+
+    fn users(email_type: &str, estimated_rows: u64) -> Value {
+        json!({
+            "table_name": "users",
+            "engine": "InnoDB",
+            "collation": "utf8mb4_0900_ai_ci",
+            "comment": "",
+            "statistics": { "estimated_rows": estimated_rows },
+            "columns": [
+                { "name": "id", "column_type": "int", "nullable": false },
+                { "name": "email", "column_type": email_type, "nullable": false }
+            ],
+            "indexes": [
+                { "name": "PRIMARY", "columns": [{ "name": "id" }], "primary": true, "unique": true, "cardinality": estimated_rows }
+            ],
+            "foreign_keys": [],
+            "check_constraints": []
+        })
+    }
+
+    #[test]
+    fn identical_snapshots_have_no_changes() {
+        let from = snapshot(json!([users("varchar(100)", 10)]));
+        assert!(diff(&from, &from).is_empty());
+    }
+
+    #[test]
+    fn statistics_are_ignored() {
+        let from = snapshot(json!([users("varchar(100)", 10)]));
+        let to = snapshot(json!([users("varchar(100)", 5000)]));
+        assert!(diff(&from, &to).is_empty());
+    }
+
+    #[test]
+    fn changed_column_lists_the_changed_properties() {
+        let from = snapshot(json!([users("varchar(100)", 10)]));
+        let to = snapshot(json!([users("varchar(255)", 10)]));
+        let changes = diff(&from, &to);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0]["kind"], "column");
+        assert_eq!(changes[0]["name"], "email");
+        assert_eq!(
+            describe(&changes[0]),
+            "~ column users.email: column_type \"varchar(100)\" -> \"varchar(255)\""
+        );
+    }
+
+    #[test]
+    fn added_and_removed_tables_and_columns() {
+        let mut to_users = users("varchar(100)", 10);
+        to_users["columns"].as_array_mut().unwrap().push(json!({ "name": "name", "column_type": "text" }));
+        let from = snapshot(json!([users("varchar(100)", 10), { "table_name": "legacy" }]));
+        let to = snapshot(json!([to_users, { "table_name": "orders" }]));
+
+        let lines: Vec<String> = diff(&from, &to).iter().map(describe).collect();
+        assert_eq!(lines, vec!["- table legacy", "+ table orders", "+ column users.name"]);
+    }
+
+    #[test]
+    fn foreign_keys_within_the_database_compare_across_database_names() {
+        let fk = |schema: &str| {
+            json!({
+                "table_name": "orders",
+                "foreign_keys": [{
+                    "name": "fk_user", "schema": schema, "columns": ["user_id"],
+                    "referenced_schema": schema, "referenced_table": "users", "referenced_columns": ["id"],
+                    "on_update": "RESTRICT", "on_delete": "CASCADE"
+                }]
+            })
+        };
+        assert!(diff(&snapshot(json!([fk("app_dev")])), &snapshot(json!([fk("app")]))).is_empty());
+    }
+
+    #[test]
+    fn load_checks_format_and_version() {
+        assert!(load(&snapshot(json!([])), None).is_ok());
+        assert!(load(&json!({ "tables": [] }), None).is_err());
+        assert!(load(&json!({ "format": FORMAT, "version": VERSION + 1 }), None).is_err());
+    }
+
+    #[test]
+    fn load_reads_files_only_inside_the_snapshot_directory() {
+        let directory = std::env::temp_dir().join(format!("mcp-server-mysql-snapshots-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("schema.json"), snapshot(json!([])).to_string()).unwrap();
+
+        assert!(load(&json!("schema.json"), Some(&directory)).is_ok());
+        assert!(load(&json!("schema.json"), None).unwrap_err().contains("--snapshot-dir"));
+        for name in ["/etc/passwd", "../schema.json", "./schema.json", "missing.json", ""] {
+            assert!(load(&json!(name), Some(&directory)).is_err(), "{name}");
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,320 @@
+// Read-only classification of queries submitted through the `query` tool.
+//
+// Queries are parsed with the MySQL dialect and walked as an AST, so column
+// names such as `updated_at` no longer trip a keyword match, while side effects
+// hidden inside an otherwise harmless SELECT are still caught.
+
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{visit_relations, Expr, Query, SetExpr, ShowStatementIn, Statement, Use, Visit, Visitor};
+use sqlparser::dialect::MySqlDialect;
+use sqlparser::parser::Parser;
+use sqlparser::tokenizer::{Token, Tokenizer, Whitespace};
+
+// Functions that block, take locks or read from the server's filesystem
+const FORBIDDEN_FUNCTIONS: &[&str] = &[
+    "SLEEP",
+    "BENCHMARK",
+    "GET_LOCK",
+    "RELEASE_LOCK",
+    "RELEASE_ALL_LOCKS",
+    "LOAD_FILE",
+    "MASTER_POS_WAIT",
+    "SOURCE_POS_WAIT",
+    "WAIT_FOR_EXECUTED_GTID_SET",
+    "WAIT_UNTIL_SQL_THREAD_AFTER_GTIDS",
+];
+
+/// Returns `Ok(())` if `sql` is a single read-only statement, otherwise the reason it was rejected.
+pub fn check_read_only(sql: &str) -> Result<(), String> {
+    let dialect = MySqlDialect {};
+
+    // MySQL executes the contents of `/*! ... */` comments and MariaDB also those of `/*M! ... */`,
+    // the parser treats both as plain comments
+    let tokens = Tokenizer::new(&dialect, sql)
+        .tokenize()
+        .map_err(|e| format!("Could not parse query: {e}"))?;
+    let has_executable_comment = tokens.iter().any(|token| {
+        matches!(token, Token::Whitespace(Whitespace::MultiLineComment(comment))
+            if comment.starts_with('!') || comment.starts_with("M!") || comment.starts_with("m!"))
+    });
+    if has_executable_comment {
+        return Err("Executable comments (/*! ... */ and /*M! ... */) are not allowed".to_string());
+    }
+
+    // The parser does not understand `INTO OUTFILE` / `INTO DUMPFILE`, name them before it fails
+    let file_export = tokens.iter().find_map(|token| match token {
+        Token::Word(word) if word.quote_style.is_none() => {
+            let keyword = word.value.to_uppercase();
+            matches!(keyword.as_str(), "OUTFILE" | "DUMPFILE").then_some(keyword)
+        }
+        _ => None,
+    });
+    if let Some(keyword) = file_export {
+        return Err(format!("SELECT ... INTO {keyword} is not allowed"));
+    }
+
+    let statements = Parser::parse_sql(&dialect, sql).map_err(|e| format!("Could not parse query: {e}"))?;
+    let statement = match statements.as_slice() {
+        [] => return Err("Query is empty".to_string()),
+        [statement] => statement,
+        _ => return Err(format!("Only a single statement is allowed, found {}", statements.len())),
+    };
+
+    match statement.visit(&mut ReadOnlyVisitor) {
+        ControlFlow::Continue(()) => Ok(()),
+        ControlFlow::Break(reason) => Err(reason),
+    }
+}
+
+struct ReadOnlyVisitor;
+
+impl Visitor for ReadOnlyVisitor {
+    type Break = String;
+
+    fn pre_visit_statement(&mut self, statement: &Statement) -> ControlFlow<String> {
+        match statement {
+            Statement::Query(_)
+            | Statement::ShowFunctions { .. }
+            | Statement::ShowVariable { .. }
+            | Statement::ShowStatus { .. }
+            | Statement::ShowVariables { .. }
+            | Statement::ShowCreate { .. }
+            | Statement::ShowColumns { .. }
+            | Statement::ShowDatabases { .. }
+            | Statement::ShowSchemas { .. }
+            | Statement::ShowTables { .. }
+            | Statement::ShowViews { .. }
+            | Statement::ShowCollation { .. }
+            | Statement::ExplainTable { .. }
+            | Statement::Explain { .. } => ControlFlow::Continue(()),
+            other => ControlFlow::Break(format!("{} statements are not allowed", statement_keyword(other))),
+        }
+    }
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<String> {
+        if !query.locks.is_empty() {
+            return ControlFlow::Break("Locking reads (FOR UPDATE / FOR SHARE) are not allowed".to_string());
+        }
+        check_set_expr(&query.body)
+    }
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<String> {
+        if let Expr::Function(function) = expr {
+            // MySQL resolves `SLEEP`(1) and mysql.sleep(1) to the built-in too, so compare the
+            // unquoted last part of the name
+            let name = function.name.0.last().map(|ident| ident.value.to_uppercase()).unwrap_or_default();
+            if FORBIDDEN_FUNCTIONS.contains(&name.as_str()) {
+                return ControlFlow::Break(format!("Function {name}() is not allowed in read-only queries"));
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+fn check_set_expr(body: &SetExpr) -> ControlFlow<String> {
+    match body {
+        SetExpr::Select(select) if select.into.is_some() => {
+            ControlFlow::Break("SELECT ... INTO is not allowed".to_string())
+        }
+        SetExpr::SetOperation { left, right, .. } => {
+            check_set_expr(left)?;
+            check_set_expr(right)
+        }
+        // Nested queries and statements are checked when the visitor reaches them
+        _ => ControlFlow::Continue(()),
+    }
+}
+
+// First keyword of a statement, e.g. `INSERT` or `CREATE`, used in rejection messages
+fn statement_keyword(statement: &Statement) -> String {
+    statement
+        .to_string()
+        .split_whitespace()
+        .next()
+        .unwrap_or("Unknown")
+        .to_uppercase()
+}
+
+/// Returns true if `sql` is a single SELECT statement, the only kind `MAX_EXECUTION_TIME` applies to.
+pub fn is_select(sql: &str) -> bool {
+    matches!(Parser::parse_sql(&MySqlDialect {}, sql).as_deref(), Ok([Statement::Query(_)]))
+}
+
+/// Returns true if `sql` is a single SELECT without a top-level ORDER BY. MySQL may return its
+/// rows in a different order each time it runs, so pages of its result can repeat or skip rows.
+pub fn is_unordered_select(sql: &str) -> bool {
+    matches!(Parser::parse_sql(&MySqlDialect {}, sql).as_deref(), Ok([Statement::Query(query)]) if query.order_by.is_none())
+}
+
+/// Returns the databases `sql` names explicitly: through qualified names such as `other.orders`,
+/// `USE` and `SHOW TABLES FROM`. Unqualified tables belong to the current database and are not
+/// listed.
+pub fn referenced_databases(sql: &str) -> Result<Vec<String>, String> {
+    let statements = Parser::parse_sql(&MySqlDialect {}, sql).map_err(|e| format!("Could not parse query: {e}"))?;
+    let mut databases = Vec::new();
+    let _ = visit_relations(&statements, |name| {
+        if let [database, _, ..] = name.0.as_slice() {
+            databases.push(database.value.clone());
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    for statement in &statements {
+        let database = match statement {
+            Statement::Use(Use::Object(name) | Use::Database(name) | Use::Schema(name)) => name.0.first(),
+            // The visitor sees this name as a table, but it is a database
+            Statement::ShowTables { show_options, .. } | Statement::ShowViews { show_options, .. } => {
+                match &show_options.show_in {
+                    Some(ShowStatementIn { parent_name: Some(name), .. }) => name.0.first(),
+                    _ => None,
+                }
+            }
+            Statement::ShowCreate { obj_name, .. } if obj_name.0.len() > 1 => obj_name.0.first(),
+            _ => None,
+        };
+        databases.extend(database.map(|database| database.value.clone()));
+    }
+    Ok(databases)
+}
+
+/// Returns true if any statement in `sql` starts with CREATE, ALTER, DROP or RENAME, so cached
+/// schemas may be out of date after it runs. SQL that cannot be tokenized counts as a change.
+pub fn is_schema_change(sql: &str) -> bool {
+    let Ok(tokens) = Tokenizer::new(&MySqlDialect {}, sql).tokenize() else {
+        return true;
+    };
+    let mut statement_start = true;
+    for token in tokens {
+        match token {
+            Token::Whitespace(_) => {}
+            Token::SemiColon => statement_start = true,
+            Token::Word(word)
+                if statement_start
+                    && word.quote_style.is_none()
+                    && matches!(word.value.to_uppercase().as_str(), "CREATE" | "ALTER" | "DROP" | "RENAME") =>
+            {
+                return true;
+            }
+            _ => statement_start = false,
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rejected(sql: &str) -> String {
+        check_read_only(sql).expect_err(sql)
+    }
+
+    #[test]
+    fn allows_read_only_statements() {
+        for sql in [
+            "SELECT id, updated_at, created_by FROM orders WHERE deleted_at IS NULL",
+            "WITH recent AS (SELECT * FROM orders WHERE created_at > NOW() - INTERVAL 1 DAY) SELECT COUNT(*) FROM recent",
+            "SELECT a FROM t UNION SELECT b FROM u",
+            "SHOW TABLES",
+            "SHOW CREATE TABLE orders",
+            "SHOW COLUMNS FROM orders",
+            "DESCRIBE orders",
+            "EXPLAIN SELECT * FROM orders",
+            "SELECT 1 -- trailing comment",
+            "SELECT 1 /* plain comment */",
+        ] {
+            assert_eq!(check_read_only(sql), Ok(()), "{sql}");
+        }
+    }
+
+    #[test]
+    fn rejects_writes() {
+        assert!(rejected("UPDATE orders SET total = 0").contains("UPDATE"));
+        assert!(rejected("DELETE FROM orders").contains("DELETE"));
+        assert!(rejected("CREATE TABLE t (id INT)").contains("CREATE"));
+        assert!(rejected("EXPLAIN ANALYZE DELETE FROM orders").contains("DELETE"));
+    }
+
+    #[test]
+    fn rejects_file_exports_and_select_into() {
+        assert!(rejected("SELECT * FROM orders INTO OUTFILE '/tmp/orders.csv'").contains("OUTFILE"));
+        assert!(rejected("SELECT * FROM orders INTO DUMPFILE '/tmp/orders'").contains("DUMPFILE"));
+        assert!(rejected("SELECT * INTO backup FROM orders").contains("INTO"));
+    }
+
+    #[test]
+    fn rejects_stacked_statements() {
+        assert!(rejected("SELECT 1; DROP TABLE orders").contains("single statement"));
+        assert!(rejected("").contains("empty"));
+    }
+
+    #[test]
+    fn rejects_executable_comments() {
+        assert!(rejected("SELECT 1 /*! , SLEEP(5) */").contains("Executable comments"));
+        assert!(rejected("SELECT 1 /*!50000 , SLEEP(5) */").contains("Executable comments"));
+        assert!(rejected("SELECT 1 /*M! , SLEEP(5) */").contains("Executable comments"));
+    }
+
+    #[test]
+    fn rejects_locking_reads() {
+        assert!(rejected("SELECT * FROM orders FOR UPDATE").contains("Locking"));
+    }
+
+    #[test]
+    fn rejects_forbidden_functions_however_they_are_named() {
+        for sql in [
+            "SELECT SLEEP(5)",
+            "SELECT sleep(5)",
+            "SELECT `SLEEP`(5)",
+            "SELECT `LOAD_FILE`('/etc/passwd')",
+            "SELECT mysql.sleep(1)",
+            "SELECT `mysql`.`GET_LOCK`('a', 10)",
+            "SELECT * FROM orders WHERE id = (SELECT BENCHMARK(1000000, MD5('a')))",
+        ] {
+            assert!(rejected(sql).contains("is not allowed"), "{sql}");
+        }
+    }
+
+    #[test]
+    fn recognises_selects() {
+        assert!(is_select("SELECT 1"));
+        assert!(is_select("WITH x AS (SELECT 1) SELECT * FROM x"));
+        assert!(!is_select("SHOW TABLES"));
+        assert!(!is_select("SELECT 1; SELECT 2"));
+    }
+
+    #[test]
+    fn recognises_unordered_selects() {
+        assert!(is_unordered_select("SELECT * FROM orders"));
+        assert!(is_unordered_select("SELECT * FROM (SELECT * FROM orders ORDER BY id) AS sorted"));
+        assert!(!is_unordered_select("SELECT * FROM orders ORDER BY id"));
+        assert!(!is_unordered_select("SELECT a FROM t UNION SELECT b FROM u ORDER BY 1"));
+        assert!(!is_unordered_select("SHOW TABLES"));
+    }
+
+    #[test]
+    fn detects_schema_changes() {
+        assert!(is_schema_change("CREATE TABLE t (id INT)"));
+        assert!(is_schema_change("  /* note */ drop table t"));
+        assert!(is_schema_change("SELECT 1; ALTER TABLE t ADD c INT"));
+        assert!(is_schema_change("RENAME TABLE a TO b"));
+        assert!(!is_schema_change("SELECT created_at FROM `drop`"));
+        assert!(!is_schema_change("INSERT INTO t VALUES ('create')"));
+        assert!(!is_schema_change("UPDATE t SET altered = 1"));
+    }
+
+    #[test]
+    fn lists_referenced_databases() {
+        let databases = |sql: &str| referenced_databases(sql).unwrap();
+        assert!(databases("SELECT * FROM orders o JOIN customers c ON c.id = o.customer_id").is_empty());
+        assert!(databases("WITH recent AS (SELECT 1) SELECT * FROM recent").is_empty());
+        assert_eq!(databases("SELECT * FROM other_db.secret"), ["other_db"]);
+        assert_eq!(databases("SELECT * FROM `Other DB`.`secret`"), ["Other DB"]);
+        assert_eq!(databases("SELECT * FROM orders WHERE id IN (SELECT id FROM billing.invoices)"), ["billing"]);
+        assert_eq!(databases("USE other_db"), ["other_db"]);
+        assert_eq!(databases("SHOW TABLES FROM other_db"), ["other_db"]);
+        assert_eq!(databases("SHOW COLUMNS FROM secret FROM other_db"), ["other_db"]);
+        assert_eq!(databases("SHOW CREATE TABLE other_db.secret"), ["other_db"]);
+        assert!(referenced_databases("SELECT FROM WHERE").is_err());
+    }
+}